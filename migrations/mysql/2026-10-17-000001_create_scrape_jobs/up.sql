@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS scrape_jobs (
+    id           BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    run_id       VARCHAR(64)  NOT NULL,
+    user_id      BIGINT UNSIGNED NOT NULL,
+    strava_id    VARCHAR(255) NOT NULL,
+    status       VARCHAR(16)  NOT NULL DEFAULT 'pending',
+    attempts     INT          NOT NULL DEFAULT 0,
+    leased_by    VARCHAR(255),
+    leased_until TIMESTAMP    NULL,
+    last_error   TEXT,
+    created_at   TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at   TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY scrape_jobs_run_strava (run_id, strava_id),
+    KEY scrape_jobs_run_status (run_id, status)
+);
@@ -0,0 +1,3 @@
+DROP INDEX scrap_logs_strava_status ON scrap_logs;
+DROP INDEX scrap_logs_run_status ON scrap_logs;
+ALTER TABLE scrap_logs DROP COLUMN run_id;
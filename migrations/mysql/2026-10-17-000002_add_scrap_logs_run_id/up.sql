@@ -0,0 +1,3 @@
+ALTER TABLE scrap_logs ADD COLUMN run_id VARCHAR(64) NULL;
+CREATE INDEX scrap_logs_run_status ON scrap_logs (run_id, status);
+CREATE INDEX scrap_logs_strava_status ON scrap_logs (strava_id, status);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS athlete_cursors (
+    strava_id          VARCHAR(255)    NOT NULL PRIMARY KEY,
+    user_id            BIGINT UNSIGNED NOT NULL,
+    last_scraped_at    DATETIME        NOT NULL,
+    last_activity_date DATETIME        NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS athlete_backfills (
+    strava_id  VARCHAR(255) NOT NULL,
+    month      VARCHAR(6)   NOT NULL,
+    created_at TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (strava_id, month)
+);
@@ -0,0 +1,8 @@
+ALTER TABLE strava_activities
+    DROP COLUMN pool_length_m,
+    DROP COLUMN swim_pace_sec_per_100m,
+    DROP COLUMN max_power_w,
+    DROP COLUMN weighted_power_w,
+    DROP COLUMN avg_power_w,
+    DROP COLUMN max_speed_kmh,
+    DROP COLUMN avg_speed_kmh;
@@ -0,0 +1,8 @@
+ALTER TABLE strava_activities
+    ADD COLUMN avg_speed_kmh FLOAT NULL,
+    ADD COLUMN max_speed_kmh FLOAT NULL,
+    ADD COLUMN avg_power_w INT NULL,
+    ADD COLUMN weighted_power_w INT NULL,
+    ADD COLUMN max_power_w INT NULL,
+    ADD COLUMN swim_pace_sec_per_100m SMALLINT NULL,
+    ADD COLUMN pool_length_m FLOAT NULL;
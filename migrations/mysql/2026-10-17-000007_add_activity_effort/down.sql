@@ -0,0 +1,8 @@
+DROP INDEX strava_activities_workout_label ON strava_activities;
+
+ALTER TABLE strava_activities
+    DROP COLUMN workout_label,
+    DROP COLUMN workout_type,
+    DROP COLUMN use_timer_time,
+    DROP COLUMN avg_temp,
+    DROP COLUMN avg_hr;
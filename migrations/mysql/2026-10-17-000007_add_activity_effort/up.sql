@@ -0,0 +1,8 @@
+ALTER TABLE strava_activities
+    ADD COLUMN avg_hr FLOAT NULL,
+    ADD COLUMN avg_temp FLOAT NULL,
+    ADD COLUMN use_timer_time TINYINT NULL,
+    ADD COLUMN workout_type SMALLINT NULL,
+    ADD COLUMN workout_label VARCHAR(16) NULL;
+
+CREATE INDEX strava_activities_workout_label ON strava_activities (workout_label);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS scrap_logs;
+DROP TABLE IF EXISTS strava_activities;
+DROP TABLE IF EXISTS users;
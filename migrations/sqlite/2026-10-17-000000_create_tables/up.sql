@@ -0,0 +1,37 @@
+CREATE TABLE IF NOT EXISTS users (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    name       TEXT      NOT NULL,
+    email      TEXT      NOT NULL,
+    strava_id  TEXT      NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS strava_activities (
+    activity_id     BIGINT PRIMARY KEY NOT NULL,
+    strava_id       TEXT      NOT NULL,
+    distance_m      INTEGER,
+    elev_gain_m     INTEGER,
+    moving_time_s   INTEGER,
+    elapsed_time_s  INTEGER,
+    pace_sec_per_km SMALLINT,
+    pace_text       TEXT,
+    calories        FLOAT,
+    avg_cadence     FLOAT,
+    trainer         SMALLINT,
+    sport_type      TEXT,
+    athlete_name    TEXT,
+    payload         TEXT      NOT NULL,
+    scraped_at      TIMESTAMP NOT NULL,
+    activity_date   TEXT
+);
+
+CREATE INDEX IF NOT EXISTS strava_activities_strava_id ON strava_activities (strava_id);
+
+CREATE TABLE IF NOT EXISTS scrap_logs (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id     BIGINT    NOT NULL,
+    strava_id   TEXT      NOT NULL,
+    activity_id TEXT      NOT NULL,
+    status      TEXT      NOT NULL,
+    created_at  TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS scrape_jobs (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id       TEXT      NOT NULL,
+    user_id      BIGINT    NOT NULL,
+    strava_id    TEXT      NOT NULL,
+    status       TEXT      NOT NULL DEFAULT 'pending',
+    attempts     INTEGER   NOT NULL DEFAULT 0,
+    leased_by    TEXT,
+    leased_until TIMESTAMP,
+    last_error   TEXT,
+    created_at   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS scrape_jobs_run_strava ON scrape_jobs (run_id, strava_id);
+CREATE INDEX IF NOT EXISTS scrape_jobs_run_status ON scrape_jobs (run_id, status);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS scrap_logs_strava_status;
+DROP INDEX IF EXISTS scrap_logs_run_status;
+ALTER TABLE scrap_logs DROP COLUMN run_id;
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS athlete_cursors (
+    strava_id          TEXT      PRIMARY KEY NOT NULL,
+    user_id            BIGINT    NOT NULL,
+    last_scraped_at    TIMESTAMP NOT NULL,
+    last_activity_date TIMESTAMP
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS athlete_backfills (
+    strava_id  TEXT      NOT NULL,
+    month      TEXT      NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (strava_id, month)
+);
@@ -0,0 +1,7 @@
+ALTER TABLE strava_activities DROP COLUMN pool_length_m;
+ALTER TABLE strava_activities DROP COLUMN swim_pace_sec_per_100m;
+ALTER TABLE strava_activities DROP COLUMN max_power_w;
+ALTER TABLE strava_activities DROP COLUMN weighted_power_w;
+ALTER TABLE strava_activities DROP COLUMN avg_power_w;
+ALTER TABLE strava_activities DROP COLUMN max_speed_kmh;
+ALTER TABLE strava_activities DROP COLUMN avg_speed_kmh;
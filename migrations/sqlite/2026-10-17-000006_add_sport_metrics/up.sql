@@ -0,0 +1,7 @@
+ALTER TABLE strava_activities ADD COLUMN avg_speed_kmh FLOAT;
+ALTER TABLE strava_activities ADD COLUMN max_speed_kmh FLOAT;
+ALTER TABLE strava_activities ADD COLUMN avg_power_w INTEGER;
+ALTER TABLE strava_activities ADD COLUMN weighted_power_w INTEGER;
+ALTER TABLE strava_activities ADD COLUMN max_power_w INTEGER;
+ALTER TABLE strava_activities ADD COLUMN swim_pace_sec_per_100m SMALLINT;
+ALTER TABLE strava_activities ADD COLUMN pool_length_m FLOAT;
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS strava_activities_workout_label;
+
+ALTER TABLE strava_activities DROP COLUMN workout_label;
+ALTER TABLE strava_activities DROP COLUMN workout_type;
+ALTER TABLE strava_activities DROP COLUMN use_timer_time;
+ALTER TABLE strava_activities DROP COLUMN avg_temp;
+ALTER TABLE strava_activities DROP COLUMN avg_hr;
@@ -0,0 +1,7 @@
+ALTER TABLE strava_activities ADD COLUMN avg_hr FLOAT;
+ALTER TABLE strava_activities ADD COLUMN avg_temp FLOAT;
+ALTER TABLE strava_activities ADD COLUMN use_timer_time SMALLINT;
+ALTER TABLE strava_activities ADD COLUMN workout_type SMALLINT;
+ALTER TABLE strava_activities ADD COLUMN workout_label TEXT;
+
+CREATE INDEX IF NOT EXISTS strava_activities_workout_label ON strava_activities (workout_label);
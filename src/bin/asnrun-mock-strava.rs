@@ -0,0 +1,63 @@
+#[macro_use]
+extern crate log;
+use std::{net::SocketAddr, path::PathBuf};
+
+use asnrun_scrapper::{MockResponse, MockStrava};
+use clap::Parser;
+
+/// Local mock of strava.com serving fixture pages for integration tests
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Directory with `athletes/<id>.html` and `activities/<id>.html` fixtures
+    #[arg(short, long, value_name = "DIR")]
+    fixtures: PathBuf,
+
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// Always answer a path, e.g. `/athletes/123=logged-out` or `/activities/9/overview=503`
+    #[arg(long, value_name = "PATH=RESPONSE", value_parser = parse_rule)]
+    respond: Vec<(String, MockResponse)>,
+
+    /// Answer the next requests to a path in order, e.g. `/athletes/123=429,429,fixture`
+    #[arg(long, value_name = "PATH=RESPONSE,...", value_parser = parse_script)]
+    script: Vec<(String, Vec<MockResponse>)>,
+}
+
+fn parse_rule(s: &str) -> Result<(String, MockResponse), String> {
+    let (path, response) = s.split_once('=').ok_or("Expected PATH=RESPONSE")?;
+
+    Ok((path.to_string(), response.parse()?))
+}
+
+fn parse_script(s: &str) -> Result<(String, Vec<MockResponse>), String> {
+    let (path, responses) = s.split_once('=').ok_or("Expected PATH=RESPONSE,...")?;
+    let responses = responses
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((path.to_string(), responses))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let mock = MockStrava::new(&args.fixtures);
+    for (path, response) in args.respond {
+        mock.respond(&path, response);
+    }
+    for (path, responses) in args.script {
+        mock.script(&path, responses);
+    }
+
+    let addr = mock.start(args.listen).await.map_err(|e| e.to_string())?;
+    info!("Mock Strava listening on http://{addr}");
+
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
@@ -1,14 +1,31 @@
 #[macro_use]
 extern crate log;
-use asnrun_scrapper::Scrapper;
-use clap::Parser;
-use futures::future;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use asnrun_scrapper::{
+    ActivityStore, Checkpoint, Config, Daemon, DryRunStore, DryRunUsers, Fetcher, HttpFetcher,
+    RecordingFetcher, ReplayFetcher, Scrapper, SportType, connect_read_only, month_interval,
+    parse_month,
+};
+use chrono::Utc;
+use clap::{Parser, Subcommand};
 use tokio::task;
 
+/// Exit code when the run stopped because the Strava session expired.
+const EXIT_SESSION_EXPIRED: i32 = 77;
+
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short, long)]
     limit: Option<i64>,
 
@@ -17,6 +34,260 @@ struct Args {
 
     #[arg(short, long)]
     jobs: Option<i64>,
+
+    /// Job queue to work on, pass the same id to every process of a run
+    #[arg(long, value_name = "ID")]
+    run_id: Option<String>,
+
+    /// Fetch and parse without touching the database, printing the parsed
+    /// activities as JSON Lines
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Strava IDs of the athletes to scrape in a dry run, no database needed
+    #[arg(long, value_name = "IDS", value_delimiter = ',', requires = "dry_run")]
+    athletes: Vec<String>,
+
+    /// File receiving the JSON Lines of a dry run, defaults to stdout
+    #[arg(long, value_name = "FILE", requires = "dry_run")]
+    output: Option<PathBuf>,
+
+    /// Continue the run interrupted by the last SIGINT/SIGTERM
+    #[arg(long, conflicts_with_all = ["run_id", "offset", "limit", "dry_run"])]
+    resume: bool,
+
+    /// Save every fetched page into the given directory
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Serve pages from a directory written by `--record` instead of the network
+    #[arg(long, value_name = "DIR")]
+    replay: Option<PathBuf>,
+
+    /// Config file, defaults to `./asnrun.toml` when it exists
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Base URL of the Strava website, e.g. a local mock server
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Cookie file with Strava sessions
+    #[arg(long, value_name = "FILE")]
+    cookies: Option<PathBuf>,
+
+    /// Proxy list file, used when `http.use_proxy` is enabled
+    #[arg(long, value_name = "FILE")]
+    proxies: Option<PathBuf>,
+
+    /// Requests sent to Strava per second by the whole process
+    #[arg(long, value_name = "RATE")]
+    requests_per_sec: Option<f64>,
+
+    /// Number of athletes read from the database at once when queueing a run
+    #[arg(long)]
+    batch_size: Option<usize>,
+
+    /// Maximum number of tasks running at once in a worker
+    #[arg(long)]
+    max_tasks: Option<usize>,
+
+    /// Skip athletes scraped less than this many minutes ago
+    #[arg(long, value_name = "MINUTES")]
+    min_interval: Option<u64>,
+
+    /// Shard of the athletes scraped by this host, in `0..shard-count`
+    #[arg(long, value_name = "INDEX", requires = "shard_count")]
+    shard_index: Option<u64>,
+
+    /// Number of hosts sharing the athletes by hashed user id
+    #[arg(long, value_name = "COUNT", requires = "shard_index")]
+    shard_count: Option<u64>,
+
+    /// Activity types to scrape, e.g. Run,TrailRun,Walk,Ride
+    #[arg(long, value_name = "TYPES", value_delimiter = ',', value_parser = SportType::parse_known)]
+    sports: Vec<SportType>,
+
+    /// Backfill athlete feeds from this month on
+    #[arg(long, value_name = "YYYY-MM")]
+    since: Option<String>,
+
+    /// Last month to backfill, defaults to the current month
+    #[arg(long, value_name = "YYYY-MM", requires = "since")]
+    until: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Keep running scrape cycles on a schedule
+    Daemon {
+        /// Minutes between the starts of two cycles
+        #[arg(long, value_name = "MINUTES", conflicts_with = "cron")]
+        every: Option<u64>,
+
+        /// Cron expression of the cycle starts, in UTC, e.g. "0 */2 * * *"
+        #[arg(long, value_name = "EXPR")]
+        cron: Option<String>,
+
+        /// File keeping the time of the last successful cycle
+        #[arg(long, value_name = "FILE")]
+        state: Option<PathBuf>,
+    },
+
+    /// Scrape one athlete or activity right away
+    Scrape {
+        #[command(subcommand)]
+        target: ScrapeTarget,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ScrapeTarget {
+    /// Scrape the activities of an athlete
+    Athlete {
+        strava_id: String,
+
+        /// Feed month to scrape, defaults to the current month
+        #[arg(long, value_name = "YYYY-MM")]
+        month: Option<String>,
+
+        /// Scrape activities already saved again and replace them
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Scrape one activity, found in the feed of its athlete
+    ///
+    /// The overview page of an activity lacks its type, name, date and
+    /// athlete, which are read from the entry of the activity in its athlete's
+    /// feed. The feed is fetched by athlete and month, hence `--athlete` and
+    /// `--month`.
+    Activity {
+        activity_id: String,
+
+        /// Strava ID of the athlete owning the activity, whose feed lists it
+        #[arg(long, value_name = "STRAVA_ID")]
+        athlete: String,
+
+        /// Feed month listing the activity, defaults to the current month
+        #[arg(long, value_name = "YYYY-MM")]
+        month: Option<String>,
+
+        /// Scrape the activity again and replace it when already saved
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+impl ScrapeTarget {
+    /// Feed interval (`YYYYMM`) of the `--month` option.
+    fn interval(&self) -> Result<String, String> {
+        let (Self::Athlete { month, .. } | Self::Activity { month, .. }) = self;
+
+        match month {
+            Some(month) => parse_month(month)
+                .map(month_interval)
+                .ok_or_else(|| format!("Invalid month {month}, expected YYYY-MM")),
+            None => Ok(month_interval(Utc::now().date_naive())),
+        }
+    }
+}
+
+impl Args {
+    /// Load the config file and apply the flags on top of it.
+    fn load_config(&self) -> Result<Config, asnrun_scrapper::Error> {
+        let mut config = Config::load(self.config.as_deref())?;
+
+        if let Some(base_url) = &self.base_url {
+            config.http.base_url = base_url.clone();
+        }
+        if let Some(cookies) = &self.cookies {
+            config.http.cookies_path = cookies.clone();
+        }
+        if let Some(proxies) = &self.proxies {
+            config.http.proxies_path = proxies.clone();
+        }
+        if let Some(requests_per_sec) = self.requests_per_sec {
+            config.http.requests_per_sec = requests_per_sec;
+        }
+        if let Some(batch_size) = self.batch_size {
+            config.scrapper.batch_size = batch_size;
+        }
+        if let Some(max_tasks) = self.max_tasks {
+            config.scrapper.max_concurrent_tasks = max_tasks;
+        }
+        if let Some(min_interval) = self.min_interval {
+            config.scrapper.min_interval_mins = min_interval;
+        }
+        if let (Some(shard_index), Some(shard_count)) = (self.shard_index, self.shard_count) {
+            config.scrapper.shard_index = shard_index;
+            config.scrapper.shard_count = shard_count;
+        }
+        if !self.sports.is_empty() {
+            config.scrapper.sports = self.sports.clone();
+        }
+        if let Some(since) = &self.since {
+            config.scrapper.backfill_since = Some(since.clone());
+        }
+        if let Some(until) = &self.until {
+            config.scrapper.backfill_until = Some(until.clone());
+        }
+
+        if let Some(Command::Daemon { every, cron, state }) = &self.command {
+            if let Some(every) = every {
+                config.daemon.every_mins = *every;
+                config.daemon.cron = None;
+            }
+            if let Some(cron) = cron {
+                config.daemon.cron = Some(cron.clone());
+            }
+            if let Some(state) = state {
+                config.daemon.state_path = state.clone();
+            }
+        }
+
+        if self.dry_run && !self.needs_database() {
+            config.validate_scrapper()?;
+        } else {
+            config.validate()?;
+        }
+
+        Ok(config)
+    }
+
+    /// Whether a dry run reads its athletes from the database. `scrape` and
+    /// `--athletes` name them on the command line.
+    fn needs_database(&self) -> bool {
+        self.athletes.is_empty() && !matches!(self.command, Some(Command::Scrape { .. }))
+    }
+}
+
+/// Stop taking new athletes on the first SIGINT/SIGTERM, exit right away on
+/// the second one.
+async fn handle_signals(scrapper: Scrapper) {
+    wait_signal().await;
+    warn!("Shutting down after the athletes being scraped, signal again to exit now");
+    scrapper.shutdown();
+
+    wait_signal().await;
+    warn!("Exiting now, unfinished athletes are retried once their lease expires");
+    std::process::exit(130);
+}
+
+#[cfg(unix)]
+async fn wait_signal() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen to SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_signal() {
+    tokio::signal::ctrl_c().await.ok();
 }
 
 #[tokio::main]
@@ -28,29 +299,161 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let offset: i64 = args.offset.unwrap_or(0);
     let limit = args.limit;
 
+    let config = args.load_config().map_err(|e| e.to_string())?;
+    info!("Using config:\n{config}");
+
     info!(
         "Starting scrapper with {jobs} jobs",
         jobs = args.jobs.unwrap_or(1)
     );
 
-    let scrapper = Scrapper::default();
-
-    if let Some(jobs) = args.jobs {
-        let tasks: Vec<_> = (0..jobs)
-            .map(|id| {
-                let scrapper = scrapper.clone();
-                task::spawn(async move {
-                    if let Err(e) = scrapper.run_worker(0, None, Some(jobs), id).await {
-                        eprintln!("Worker {} failed: {:?}", id, e);
-                    }
-                })
-            })
-            .collect();
-
-        future::join_all(tasks).await;
+    let http = HttpFetcher::new(config.http.clone());
+    let fetcher: Arc<dyn Fetcher> = match (&args.record, &args.replay) {
+        (Some(dir), _) => {
+            info!("Recording responses into {}", dir.display());
+            Arc::new(RecordingFetcher::new(Arc::new(http), dir))
+        }
+        (_, Some(dir)) => {
+            info!("Replaying responses from {}", dir.display());
+            Arc::new(ReplayFetcher::new(dir))
+        }
+        _ => Arc::new(http),
+    };
+
+    let store = if args.dry_run {
+        let users = if args.needs_database() {
+            DryRunUsers::Store(connect_read_only(&config.database).map_err(|e| e.to_string())?)
+        } else {
+            DryRunUsers::from_strava_ids(&args.athletes)
+        };
+        let output: Box<dyn Write + Send> = match &args.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout()),
+        };
+        info!("Dry run, parsed activities are not saved");
+
+        Some(Arc::new(DryRunStore::new(users, output)) as Arc<dyn ActivityStore>)
+    } else {
+        None
+    };
+
+    let daemon_config = config.daemon.clone();
+    let checkpoint_path = config.scrapper.checkpoint_path.clone();
+    let mut builder = Scrapper::builder().config(config).fetcher(fetcher);
+    if let Some(store) = store {
+        builder = builder.store(store);
+    }
+    let scrapper = builder.build().map_err(|e| e.to_string())?;
+
+    if let Some(Command::Scrape { target }) = &args.command {
+        let interval = target.interval()?;
+        let saved = match target {
+            ScrapeTarget::Athlete {
+                strava_id, force, ..
+            } => {
+                scrapper
+                    .scrape_single_athlete(strava_id, &interval, *force)
+                    .await
+            }
+            ScrapeTarget::Activity {
+                activity_id,
+                athlete,
+                force,
+                ..
+            } => {
+                scrapper
+                    .scrape_single_activity(activity_id, athlete, &interval, *force)
+                    .await
+            }
+        }
+        .map_err(|e| e.to_string())?;
+        info!("Saved {saved} activities");
+
+        return Ok(());
+    }
+
+    let jobs = args.jobs.unwrap_or(1);
+    task::spawn(handle_signals(scrapper.clone()));
+
+    if let Some(Command::Daemon { .. }) = args.command {
+        let daemon = Daemon::new(scrapper.clone(), &daemon_config).map_err(|e| e.to_string())?;
+        let result = daemon.run(offset, limit, jobs).await;
+        exit_if_session_expired(&scrapper, false);
+
+        return result.map_err(|e| e.to_string().into());
+    }
+
+    let (run_id, offset, limit) = if args.resume {
+        let checkpoint = Checkpoint::load(&checkpoint_path)
+            .map_err(|e| format!("No run to resume in {}: {e}", checkpoint_path.display()))?;
+        info!(
+            "Resuming run {} interrupted at {}, {} athletes done, {} failed",
+            checkpoint.run_id,
+            checkpoint.interrupted_at,
+            checkpoint.jobs.done,
+            checkpoint.jobs.failed
+        );
+
+        (checkpoint.run_id, checkpoint.offset, checkpoint.limit)
     } else {
-        scrapper.run_worker(offset, limit, None, 0).await?;
+        let run_id = args
+            .run_id
+            .clone()
+            .unwrap_or_else(|| Utc::now().format("%Y%m%d%H%M%S").to_string());
+
+        (run_id, offset, limit)
+    };
+    info!("Using run id {run_id}");
+
+    let result = scrapper.run(&run_id, offset, limit, jobs).await;
+    if !scrapper.is_session_expired() {
+        result.map_err(|e| e.to_string())?;
+    }
+
+    // The job queue of a dry run is lost on exit, there is nothing to resume.
+    if args.dry_run {
+        exit_if_session_expired(&scrapper, false);
+        return Ok(());
+    }
+
+    if scrapper.is_shutdown() {
+        let checkpoint = Checkpoint {
+            jobs: scrapper
+                .job_counts(&run_id)
+                .await
+                .map_err(|e| e.to_string())?,
+            run_id,
+            offset,
+            limit,
+            interrupted_at: Utc::now(),
+        };
+        checkpoint
+            .save(&checkpoint_path)
+            .map_err(|e| e.to_string())?;
+
+        warn!(
+            "Run {} interrupted with {} athletes left, continue it with --resume",
+            checkpoint.run_id,
+            checkpoint.jobs.pending + checkpoint.jobs.leased
+        );
+    } else {
+        Checkpoint::remove(&checkpoint_path).map_err(|e| e.to_string())?;
     }
+    exit_if_session_expired(&scrapper, true);
 
     Ok(())
 }
+
+/// Exit with [`EXIT_SESSION_EXPIRED`] when the run was stopped because the
+/// Strava session expired, so schedulers can tell it from other failures.
+/// `resumable` tells whether a checkpoint was saved for `--resume`.
+fn exit_if_session_expired(scrapper: &Scrapper, resumable: bool) {
+    if scrapper.is_session_expired() {
+        if resumable {
+            error!("Strava session expired, update the cookies and run again with --resume");
+        } else {
+            error!("Strava session expired, update the cookies and run again");
+        }
+        std::process::exit(EXIT_SESSION_EXPIRED);
+    }
+}
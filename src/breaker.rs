@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::error::{Error, ParseError};
+
+/// Counts consecutive authentication failures shared by every worker, and
+/// opens once `threshold` of them happen in a row. A successful athlete resets
+/// the count, other failures leave it as is.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    failures: AtomicU32,
+    open: AtomicBool,
+}
+
+impl CircuitBreaker {
+    /// Breaker opening after `threshold` failures in a row, never when 0.
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            failures: AtomicU32::new(0),
+            open: AtomicBool::new(false),
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.failures.store(0, Ordering::SeqCst);
+    }
+
+    /// Count a failure, returns `true` only for the failure opening the
+    /// breaker.
+    pub fn record_failure(&self) -> bool {
+        let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+
+        self.threshold > 0 && failures >= self.threshold && !self.open.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures.load(Ordering::SeqCst)
+    }
+}
+
+/// Whether the error means the Strava session is not valid anymore, rather
+/// than a problem with the athlete.
+pub fn is_auth_failure(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Parse(ParseError::NotLoggedIn)
+            | Error::HttpStatus(401 | 403)
+            | Error::Unauthorized(_)
+    )
+}
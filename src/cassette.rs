@@ -0,0 +1,190 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    fetcher::{FetchResponse, Fetcher},
+};
+
+fn athlete_path(root: &Path, athlete_id: &str, interval: &str) -> PathBuf {
+    root.join("athletes")
+        .join(format!("{athlete_id}-{interval}.json"))
+}
+
+fn activity_path(root: &Path, activity_id: &str) -> PathBuf {
+    root.join("activities").join(format!("{activity_id}.json"))
+}
+
+/// Responses of one page, in the order they were fetched. Cassettes
+/// recorded before retries were kept hold a single response.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Recording {
+    Attempts(Vec<FetchResponse>),
+    Single(FetchResponse),
+}
+
+impl Recording {
+    fn into_attempts(self) -> Vec<FetchResponse> {
+        match self {
+            Recording::Attempts(attempts) => attempts,
+            Recording::Single(res) => vec![res],
+        }
+    }
+}
+
+/// Wrap another fetcher and save every response (URL, status, headers and body)
+/// into a cassette directory, so it can be served back by [`ReplayFetcher`].
+///
+/// Each attempt of a retried request is kept, in order. A page recorded by an
+/// earlier run is overwritten on its first fetch. Failing to save a response
+/// is logged and does not fail the fetch.
+pub struct RecordingFetcher {
+    inner: Arc<dyn Fetcher>,
+    root: PathBuf,
+    /// Pages recorded by this fetcher, the others are started afresh.
+    recorded: Mutex<HashSet<PathBuf>>,
+}
+
+impl RecordingFetcher {
+    pub fn new<P: AsRef<Path>>(inner: Arc<dyn Fetcher>, root: P) -> Self {
+        Self {
+            inner,
+            root: root.as_ref().to_path_buf(),
+            recorded: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn record(&self, path: PathBuf, res: &FetchResponse) {
+        // Held while writing, so attempts of the same page are appended one
+        // at a time.
+        let mut recorded = self.recorded.lock().unwrap_or_else(|e| e.into_inner());
+        let append = recorded.contains(&path);
+
+        match Self::save(&path, res, append) {
+            Ok(()) => {
+                recorded.insert(path);
+            }
+            Err(e) => error!(
+                "Failed to record {} into {}: {}",
+                res.url,
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    fn save(path: &Path, res: &FetchResponse, append: bool) -> Result<(), Error> {
+        let mut attempts = if append {
+            serde_json::from_slice::<Recording>(&fs::read(path)?)?.into_attempts()
+        } else {
+            Vec::new()
+        };
+        attempts.push(res.clone());
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(&attempts)?)?;
+
+        Ok(())
+    }
+}
+
+impl Fetcher for RecordingFetcher {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        Box::pin(async move {
+            let res = self.inner.fetch_athlete(athlete_id, interval).await?;
+            self.record(athlete_path(&self.root, athlete_id, interval), &res);
+
+            Ok(res)
+        })
+    }
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        Box::pin(async move {
+            let res = self.inner.fetch_activity_overview(activity_id).await?;
+            self.record(activity_path(&self.root, activity_id), &res);
+
+            Ok(res)
+        })
+    }
+
+    fn pause(&self, duration: Duration) {
+        self.inner.pause(duration);
+    }
+}
+
+/// Serve responses saved by [`RecordingFetcher`] without touching the network.
+///
+/// The attempts of a page are served in the order they were recorded, the
+/// last one answering any further fetch.
+pub struct ReplayFetcher {
+    root: PathBuf,
+    /// Attempts served per page.
+    served: Mutex<HashMap<PathBuf, usize>>,
+}
+
+impl ReplayFetcher {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            served: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn load(&self, path: PathBuf) -> Result<FetchResponse, Error> {
+        if !path.is_file() {
+            return Err(Error::NotFound(
+                404,
+                format!("No recorded response at {}", path.display()),
+            ));
+        }
+
+        let attempts = serde_json::from_slice::<Recording>(&fs::read(&path)?)?.into_attempts();
+        let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+        let attempt = served.entry(path.clone()).or_default();
+        let index = (*attempt).min(attempts.len().saturating_sub(1));
+        *attempt += 1;
+
+        attempts.into_iter().nth(index).ok_or_else(|| {
+            Error::NotFound(404, format!("No recorded response at {}", path.display()))
+        })
+    }
+}
+
+impl Fetcher for ReplayFetcher {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        let res = self.load(athlete_path(&self.root, athlete_id, interval));
+
+        Box::pin(async move { res })
+    }
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        let res = self.load(activity_path(&self.root, activity_id));
+
+        Box::pin(async move { res })
+    }
+}
@@ -0,0 +1,39 @@
+use std::{fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, models::JobCounts};
+
+/// Progress of an interrupted run, saved to continue it with `--resume`. The
+/// processed athletes themselves are the `done` and `failed` jobs of the run
+/// in `scrape_jobs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub run_id: String,
+    pub offset: i64,
+    pub limit: Option<i64>,
+    pub jobs: JobCounts,
+    pub interrupted_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Remove the checkpoint once its run is finished.
+    pub fn remove(path: &Path) -> Result<(), Error> {
+        if path.is_file() {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use reqwest::Client;
+
+use crate::{config::HttpConfig, error::Error, types::CookieData, utils};
+
+/// Proxies tried for one request before giving up.
+const MAX_PROXY_CHECKS: usize = 3;
+
+/// HTTP clients of the Strava sessions, one per session and proxy, each one
+/// built on its first request and reused afterwards so its connections are
+/// kept alive. A proxy is picked at random for every request. The proxy list
+/// is read once, with the first client.
+pub struct ClientPool {
+    config: HttpConfig,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// Working proxies, a proxy failing its check is removed.
+    proxies: Option<Vec<String>>,
+    /// Clients by session cookie and proxy, a session whose cookie was
+    /// updated gets a new client.
+    clients: HashMap<ClientKey, Client>,
+}
+
+type ClientKey = (String, Option<String>);
+
+/// Client of the pool, with the proxy it goes through.
+#[derive(Clone)]
+pub struct PooledClient {
+    pub client: Client,
+    pub proxy: Option<String>,
+}
+
+impl ClientPool {
+    pub fn new(config: HttpConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    /// Client sending the cookies of the session, through a random proxy
+    /// when `use_proxy` is set. A proxy is checked before its first client is
+    /// built; after [`MAX_PROXY_CHECKS`] failed checks the request fails.
+    pub async fn client(&self, session: &CookieData) -> Result<PooledClient, Error> {
+        for _ in 0..MAX_PROXY_CHECKS {
+            let proxy = self.pick_proxy()?;
+            let key = (session.cookie.clone(), proxy.clone());
+            if let Some(client) = self.lock().clients.get(&key) {
+                return Ok(PooledClient {
+                    client: client.clone(),
+                    proxy,
+                });
+            }
+
+            if let Some(proxy) = &proxy
+                && !utils::check_proxy(proxy).await
+            {
+                warn!("Proxy {proxy} is not working, dropping it");
+                self.drop_proxy(proxy);
+                continue;
+            }
+
+            debug!("Building HTTP client for {}", session.email);
+            let client = utils::build_client(&self.config, session, proxy.as_deref());
+            let client = self.lock().clients.entry(key).or_insert(client).clone();
+
+            return Ok(PooledClient { client, proxy });
+        }
+
+        Err(crate::error_custom!("No working proxy found"))
+    }
+
+    /// Forget the client of the session and proxy, after a transport error,
+    /// so the next request builds a new one.
+    pub fn evict(&self, session: &CookieData, proxy: Option<&str>) {
+        let key = (session.cookie.clone(), proxy.map(str::to_string));
+        self.lock().clients.remove(&key);
+    }
+
+    fn pick_proxy(&self) -> Result<Option<String>, Error> {
+        if !self.config.use_proxy {
+            return Ok(None);
+        }
+
+        let mut state = self.lock();
+        if state.proxies.is_none() {
+            state.proxies = Some(utils::load_proxies(&self.config.proxies_path)?);
+        }
+
+        Ok(utils::pick_proxy(
+            state.proxies.as_deref().unwrap_or_default(),
+        ))
+    }
+
+    fn drop_proxy(&self, proxy: &str) {
+        if let Some(proxies) = self.lock().proxies.as_mut() {
+            proxies.retain(|p| p != proxy);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
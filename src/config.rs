@@ -0,0 +1,393 @@
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, ErrorCode},
+    types::SportType,
+    utils::{STRAVA_URL, parse_month, shard_of},
+};
+
+/// Config file loaded when no path is given.
+pub const DEFAULT_CONFIG_PATH: &str = "./asnrun.toml";
+
+/// Activity types scraped when `scrapper.sports` is not set.
+pub const DEFAULT_SPORTS: [SportType; 3] =
+    [SportType::Run, SportType::TrailRun, SportType::VirtualRun];
+
+/// Scrapper configuration, loaded from `asnrun.toml` and overridden by
+/// environment variables and CLI flags.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub scrapper: ScrapperConfig,
+    pub http: HttpConfig,
+    pub database: DatabaseConfig,
+    pub daemon: DaemonConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrapperConfig {
+    /// Number of athletes read at once when filling the job queue.
+    pub batch_size: usize,
+    /// Number of tasks scraping athletes at once in a worker.
+    pub max_concurrent_tasks: usize,
+    /// Extra pause after each athlete, in milliseconds, on top of the
+    /// `http.requests_per_sec` limit.
+    pub athlete_delay_ms: u64,
+    /// Extra pause after each activity, in milliseconds.
+    pub activity_delay_ms: u64,
+    /// Attempts to fetch an athlete page before skipping it.
+    pub athlete_retries: u32,
+    /// Attempts to fetch an activity overview before skipping it.
+    pub activity_retries: u32,
+    /// First pause before retrying a request, doubled after each failed
+    /// attempt, in milliseconds.
+    pub retry_base_delay_ms: u64,
+    /// Longest pause before retrying a request, in milliseconds.
+    pub retry_max_delay_ms: u64,
+    /// Longest `Retry-After` waited for, in milliseconds, such as the end of
+    /// a Strava rate limit window. Requests asked to wait longer are given up.
+    pub retry_after_max_ms: u64,
+    /// How long a worker holds an athlete before another worker may take it
+    /// over, in seconds.
+    pub lease_secs: u64,
+    /// Skip athletes scraped less than this many minutes ago, 0 to scrape
+    /// everyone. Athletes without recent activity wait up to 8 times longer.
+    pub min_interval_mins: u64,
+    /// First month (`YYYY-MM`) of the athlete feeds to backfill. Every month
+    /// up to `backfill_until` is scraped once per athlete, so athletes added
+    /// later get their history on their first run.
+    pub backfill_since: Option<String>,
+    /// Last month (`YYYY-MM`) to backfill, defaults to the current month. The
+    /// current month is scraped on every run either way.
+    pub backfill_until: Option<String>,
+    /// Activity types of the feed to scrape, e.g. `Run`, `TrailRun`, `Walk`,
+    /// `Hike`, `Ride` or `Swim`.
+    pub sports: Vec<SportType>,
+    /// Stop the run after this many athletes in a row failed as not logged
+    /// in, 0 to never stop.
+    pub max_auth_failures: u32,
+    /// File written when a run is interrupted, read by `--resume`.
+    pub checkpoint_path: PathBuf,
+    /// Shard of the athletes scraped by this process, in `0..shard_count`.
+    pub shard_index: u64,
+    /// Number of hosts sharing the athletes, each one scraping the athletes
+    /// whose hashed id falls in its shard.
+    pub shard_count: u64,
+}
+
+impl Default for ScrapperConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 300,
+            max_concurrent_tasks: 50,
+            athlete_delay_ms: 0,
+            activity_delay_ms: 0,
+            athlete_retries: 5,
+            activity_retries: 8,
+            retry_base_delay_ms: 500,
+            retry_max_delay_ms: 60_000,
+            retry_after_max_ms: 900_000,
+            lease_secs: 900,
+            min_interval_mins: 0,
+            backfill_since: None,
+            backfill_until: None,
+            sports: DEFAULT_SPORTS.to_vec(),
+            max_auth_failures: 10,
+            checkpoint_path: PathBuf::from("./asnrun-checkpoint.json"),
+            shard_index: 0,
+            shard_count: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub base_url: String,
+    pub cookies_path: PathBuf,
+    pub proxies_path: PathBuf,
+    pub use_proxy: bool,
+    pub connect_timeout_secs: u64,
+    /// Requests sent to Strava per second, by all workers of the process.
+    pub requests_per_sec: f64,
+    /// Requests sent at once after an idle period, before
+    /// `requests_per_sec` applies.
+    pub burst: u32,
+}
+
+impl ScrapperConfig {
+    /// Job queue of this shard for the run, so hosts sharing a run id never
+    /// lease each other's athletes.
+    pub fn shard_run_id(&self, run_id: &str) -> String {
+        if self.shard_count > 1 {
+            format!("{run_id}-{}of{}", self.shard_index, self.shard_count)
+        } else {
+            run_id.to_owned()
+        }
+    }
+
+    /// Whether activities of the feed type are scraped.
+    pub fn collects(&self, sport: &SportType) -> bool {
+        self.sports.contains(sport)
+    }
+
+    /// Whether the user belongs to this shard, see [`shard_of`].
+    pub fn in_shard(&self, user_id: u64) -> bool {
+        self.shard_count <= 1 || shard_of(user_id, self.shard_count) == self.shard_index
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            base_url: STRAVA_URL.to_string(),
+            cookies_path: PathBuf::from("./cookies.json"),
+            proxies_path: PathBuf::from("./proxies.json"),
+            use_proxy: false,
+            connect_timeout_secs: 15,
+            requests_per_sec: 2.0,
+            burst: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_max_size: u32,
+    pub pool_min_idle: u32,
+    pub conn_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            pool_max_size: 15,
+            pool_min_idle: 3,
+            conn_timeout_secs: 10,
+            idle_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Minutes between the starts of two scrape cycles, ignored when `cron`
+    /// is set.
+    pub every_mins: u64,
+    /// Cron expression of the cycle starts, in UTC, e.g. `0 */2 * * *`.
+    pub cron: Option<String>,
+    /// File keeping the time of the last successful cycle across restarts.
+    pub state_path: PathBuf,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            every_mins: 60,
+            cron: None,
+            state_path: PathBuf::from("./asnrun-daemon.json"),
+        }
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidParameter(ErrorCode::InvalidParameter as i32, message)
+}
+
+fn env_override<T: FromStr>(target: &mut T, name: &str) -> Result<(), Error> {
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .parse()
+            .map_err(|_| invalid(format!("Invalid value for {name}: {value}")))?;
+    }
+
+    Ok(())
+}
+
+impl Config {
+    /// Load config from `path`, or from `./asnrun.toml` when it exists,
+    /// falling back to the defaults. Environment variables are applied on top.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).is_file() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+        config.apply_env()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+
+        toml::from_str(&content)
+            .map_err(|e| invalid(format!("Invalid config {}: {e}", path.display())))
+    }
+
+    /// Override values from environment variables.
+    pub fn apply_env(&mut self) -> Result<(), Error> {
+        env_override(&mut self.database.url, "DATABASE_URL")?;
+        env_override(&mut self.database.pool_max_size, "DB_POOL_MAX_SIZE")?;
+        env_override(&mut self.database.pool_min_idle, "DB_POOL_MIN_IDLE")?;
+        env_override(&mut self.database.conn_timeout_secs, "DB_CONN_TIMEOUT")?;
+        env_override(&mut self.database.idle_timeout_secs, "DB_IDLE_TIMEOUT")?;
+        env_override(&mut self.http.use_proxy, "USE_PROXY")?;
+        env_override(&mut self.http.base_url, "ASNRUN_BASE_URL")?;
+        env_override(&mut self.http.cookies_path, "ASNRUN_COOKIES_PATH")?;
+        env_override(&mut self.http.proxies_path, "ASNRUN_PROXIES_PATH")?;
+        env_override(&mut self.http.requests_per_sec, "ASNRUN_REQUESTS_PER_SEC")?;
+        env_override(&mut self.scrapper.batch_size, "ASNRUN_BATCH_SIZE")?;
+        env_override(
+            &mut self.scrapper.max_concurrent_tasks,
+            "ASNRUN_MAX_CONCURRENT_TASKS",
+        )?;
+        env_override(
+            &mut self.scrapper.min_interval_mins,
+            "ASNRUN_MIN_INTERVAL_MINS",
+        )?;
+        env_override(&mut self.scrapper.shard_index, "ASNRUN_SHARD_INDEX")?;
+        env_override(&mut self.scrapper.shard_count, "ASNRUN_SHARD_COUNT")?;
+
+        Ok(())
+    }
+
+    /// Check the values are usable before starting a run.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_scrapper()?;
+        self.validate_database()
+    }
+
+    /// Check every value but the database ones, for runs without a database.
+    pub fn validate_scrapper(&self) -> Result<(), Error> {
+        let scrapper = &self.scrapper;
+        if scrapper.batch_size == 0 {
+            return Err(invalid("scrapper.batch_size must be positive".into()));
+        }
+        if scrapper.max_concurrent_tasks == 0 {
+            return Err(invalid(
+                "scrapper.max_concurrent_tasks must be positive".into(),
+            ));
+        }
+        if scrapper.lease_secs == 0 {
+            return Err(invalid("scrapper.lease_secs must be positive".into()));
+        }
+        if scrapper.athlete_retries == 0 || scrapper.activity_retries == 0 {
+            return Err(invalid("scrapper retries must be at least 1".into()));
+        }
+        if scrapper.retry_base_delay_ms > scrapper.retry_max_delay_ms {
+            return Err(invalid(
+                "scrapper.retry_base_delay_ms must not exceed retry_max_delay_ms".into(),
+            ));
+        }
+        if scrapper.retry_after_max_ms < scrapper.retry_max_delay_ms {
+            return Err(invalid(
+                "scrapper.retry_after_max_ms must not be less than retry_max_delay_ms".into(),
+            ));
+        }
+        if scrapper.sports.is_empty() {
+            return Err(invalid("scrapper.sports must not be empty".into()));
+        }
+        if let Some(sport) = scrapper.sports.iter().find(|sport| !sport.is_known()) {
+            return Err(invalid(format!(
+                "scrapper.sports has unknown activity type `{sport}`"
+            )));
+        }
+        if scrapper.shard_index >= scrapper.shard_count {
+            return Err(invalid(
+                "scrapper.shard_index must be below a positive scrapper.shard_count".into(),
+            ));
+        }
+
+        let month = |value: &Option<String>, key: &str| match value {
+            Some(value) => parse_month(value)
+                .map(Some)
+                .ok_or_else(|| invalid(format!("scrapper.{key} must be YYYY-MM, got {value}"))),
+            None => Ok(None),
+        };
+        let since = month(&scrapper.backfill_since, "backfill_since")?;
+        let until = month(&scrapper.backfill_until, "backfill_until")?;
+        if let (Some(since), Some(until)) = (since, until)
+            && since > until
+        {
+            return Err(invalid(
+                "scrapper.backfill_since must not be after backfill_until".into(),
+            ));
+        }
+        if since.is_none() && until.is_some() {
+            return Err(invalid(
+                "scrapper.backfill_until needs backfill_since".into(),
+            ));
+        }
+
+        if self.daemon.every_mins == 0 {
+            return Err(invalid("daemon.every_mins must be positive".into()));
+        }
+        if let Some(cron) = &self.daemon.cron {
+            crate::daemon::Schedule::cron(cron)?;
+        }
+
+        if self.http.requests_per_sec.is_nan() || self.http.requests_per_sec <= 0.0 {
+            return Err(invalid("http.requests_per_sec must be positive".into()));
+        }
+        if self.http.burst == 0 {
+            return Err(invalid("http.burst must be positive".into()));
+        }
+
+        Url::parse(&self.http.base_url)
+            .map_err(|e| invalid(format!("Invalid http.base_url: {e}")))?;
+
+        Ok(())
+    }
+
+    pub fn validate_database(&self) -> Result<(), Error> {
+        let database = &self.database;
+        if database.url.is_empty() {
+            return Err(invalid("database.url (DATABASE_URL) must be set".into()));
+        }
+        if database.pool_max_size == 0 || database.pool_min_idle > database.pool_max_size {
+            return Err(invalid(
+                "database.pool_min_idle must not exceed a positive database.pool_max_size".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Copy of the config safe to print, with the database password hidden.
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+
+        if let Ok(mut url) = Url::parse(&config.database.url)
+            && url.password().is_some()
+        {
+            url.set_password(Some("*****")).ok();
+            config.database.url = url.to_string();
+        }
+
+        config
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content = toml::to_string(&self.redacted()).map_err(|_| fmt::Error)?;
+
+        write!(f, "{}", content.trim_end())
+    }
+}
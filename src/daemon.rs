@@ -0,0 +1,165 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::DaemonConfig,
+    error::{Error, ErrorCode},
+    scrapper::Scrapper,
+};
+
+/// When the daemon starts a scrape cycle.
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// Fixed time between the starts of two cycles.
+    Every(TimeDelta),
+    /// Cron expression, in UTC.
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    /// Parse a cron expression. The usual 5 fields (minute to day of week) are
+    /// accepted, as well as the 6 or 7 fields form with seconds and years.
+    pub fn cron(expression: &str) -> Result<Self, Error> {
+        let expression = match expression.split_whitespace().count() {
+            5 => format!("0 {expression}"),
+            _ => expression.to_string(),
+        };
+
+        cron::Schedule::from_str(&expression)
+            .map(|schedule| Schedule::Cron(Box::new(schedule)))
+            .map_err(|e| {
+                Error::InvalidParameter(
+                    ErrorCode::InvalidParameter as i32,
+                    format!("Invalid cron expression {expression}: {e}"),
+                )
+            })
+    }
+
+    pub fn from_config(config: &DaemonConfig) -> Result<Self, Error> {
+        match &config.cron {
+            Some(expression) => Self::cron(expression),
+            None => Ok(Schedule::Every(TimeDelta::minutes(
+                config.every_mins as i64,
+            ))),
+        }
+    }
+
+    /// Start of the first cycle after a cycle started at `last`.
+    pub fn next_after(&self, last: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(every) => Some(last + *every),
+            Schedule::Cron(schedule) => schedule.after(&last).next(),
+        }
+    }
+}
+
+/// State of the daemon saved between restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// Start of the last cycle that finished without error.
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+impl DaemonState {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// Run scrape cycles on a schedule. Cycles never overlap, a cycle running
+/// longer than the schedule delays the next one. Returns once the scrapper is
+/// shut down.
+pub struct Daemon {
+    scrapper: Scrapper,
+    schedule: Schedule,
+    state_path: PathBuf,
+}
+
+impl Daemon {
+    pub fn new(scrapper: Scrapper, config: &DaemonConfig) -> Result<Self, Error> {
+        Ok(Self {
+            scrapper,
+            schedule: Schedule::from_config(config)?,
+            state_path: config.state_path.clone(),
+        })
+    }
+
+    /// Run cycles of `jobs` workers over the athletes in `offset..offset + limit`
+    /// until shutdown. The first cycle starts right away when the last successful one
+    /// is older than the schedule.
+    pub async fn run(&self, offset: i64, limit: Option<i64>, jobs: i64) -> Result<(), Error> {
+        let mut state = DaemonState::load(&self.state_path)?;
+        let mut last_start = state.last_success;
+
+        loop {
+            let now = Utc::now();
+            let next = match last_start {
+                Some(last) => self.schedule.next_after(last).ok_or_else(|| {
+                    Error::InvalidParameter(
+                        ErrorCode::InvalidParameter as i32,
+                        "Schedule has no upcoming cycle".into(),
+                    )
+                })?,
+                None => now,
+            };
+
+            if next > now {
+                info!("[DAEMON] Next cycle at {next}");
+                tokio::select! {
+                    _ = tokio::time::sleep((next - now).to_std().unwrap_or(Duration::ZERO)) => {}
+                    _ = self.scrapper.wait_shutdown() => {}
+                }
+            }
+            if self.scrapper.is_shutdown() {
+                info!("[DAEMON] Stopped");
+                return Ok(());
+            }
+
+            let started = Utc::now();
+            let run_id = format!("daemon-{}", started.format("%Y%m%d%H%M%S"));
+            last_start = Some(started);
+            info!("[DAEMON] Starting cycle {run_id}");
+
+            match self.scrapper.run(&run_id, offset, limit, jobs).await {
+                Ok(()) if self.scrapper.is_shutdown() => {
+                    info!("[DAEMON] Cycle {run_id} interrupted");
+                    return Ok(());
+                }
+                Ok(()) => {
+                    info!(
+                        "[DAEMON] Finished cycle {run_id} in {}s",
+                        (Utc::now() - started).num_seconds()
+                    );
+                    state.last_success = Some(started);
+                    state.save(&self.state_path)?;
+                }
+                Err(e @ Error::Unauthorized(_)) => return Err(e),
+                Err(e) => error!("[DAEMON] Cycle {run_id} failed: {e}"),
+            }
+
+            // An overrun cycle is followed right away by the next one.
+            if let Some(next) = self.schedule.next_after(started)
+                && next < Utc::now()
+            {
+                warn!("[DAEMON] Cycle {run_id} took longer than the schedule");
+            }
+        }
+    }
+}
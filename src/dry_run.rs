@@ -0,0 +1,319 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::NaiveDateTime;
+use futures::future::BoxFuture;
+
+use crate::{
+    error::{Error, ErrorCode},
+    models::{
+        AthleteCursor, FailureCount, JobCounts, JobStatus, NewScrapLog, ScrapeJob, User, UserCursor,
+    },
+    store::ActivityStore,
+    types::StravaActivity,
+};
+
+/// Athletes scraped by a dry run.
+pub enum DryRunUsers {
+    /// Athletes given by Strava ID, no database is needed.
+    List(Vec<User>),
+    /// Athletes read from the database, nothing is written into it.
+    Store(Arc<dyn ActivityStore>),
+}
+
+impl DryRunUsers {
+    /// Athletes in the given order, numbered downwards so they page like
+    /// rows of `users`.
+    pub fn from_strava_ids(strava_ids: &[String]) -> Self {
+        let count = strava_ids.len() as u64;
+
+        DryRunUsers::List(
+            strava_ids
+                .iter()
+                .zip((1..=count).rev())
+                .map(|(strava_id, id)| User {
+                    id,
+                    name: String::new(),
+                    email: String::new(),
+                    strava_id: strava_id.clone(),
+                    created_at: NaiveDateTime::default(),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// [`ActivityStore`] writing parsed activities as JSON Lines instead of saving
+/// them. Every activity is fetched and parsed, whether saved before or not,
+/// and the job queue only lives in memory.
+pub struct DryRunStore {
+    users: DryRunUsers,
+    output: Mutex<Box<dyn Write + Send>>,
+    jobs: Mutex<JobQueue>,
+}
+
+/// Jobs of the dry run, a job's id being its position in `jobs` plus one.
+#[derive(Default)]
+struct JobQueue {
+    jobs: Vec<(ScrapeJob, JobStatus)>,
+    /// `(run_id, strava_id)` of the queued jobs, to enqueue an athlete once
+    /// per run.
+    queued: HashSet<(String, String)>,
+}
+
+impl DryRunStore {
+    pub fn new(users: DryRunUsers, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            users,
+            output: Mutex::new(output),
+            jobs: Mutex::new(JobQueue::default()),
+        }
+    }
+
+    fn write(&self, activities: Vec<StravaActivity>) -> Result<usize, Error> {
+        let mut output = self.output.lock().map_err(|_| poisoned())?;
+        for activity in &activities {
+            writeln!(output, "{}", serde_json::to_string(activity)?)?;
+        }
+        output.flush()?;
+
+        Ok(activities.len())
+    }
+
+    fn set_status(&self, job: &ScrapeJob, status: JobStatus) -> Result<(), Error> {
+        let mut queue = self.jobs.lock().map_err(|_| poisoned())?;
+        let index = usize::try_from(job.id - 1).ok();
+        if let Some((_, current)) = index.and_then(|index| queue.jobs.get_mut(index)) {
+            *current = status;
+        }
+
+        Ok(())
+    }
+}
+
+fn poisoned() -> Error {
+    Error::InternalError(
+        ErrorCode::UnknownError as i32,
+        failure::err_msg("Dry run store lock poisoned"),
+    )
+}
+
+impl ActivityStore for DryRunStore {
+    fn get_users<'a>(
+        &'a self,
+        after: Option<&'a UserCursor>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        Box::pin(async move {
+            match &self.users {
+                DryRunUsers::List(users) => Ok(users
+                    .iter()
+                    .filter(|user| after.is_none_or(|after| UserCursor::from(*user) < *after))
+                    .take(limit.max(0) as usize)
+                    .cloned()
+                    .collect()),
+                DryRunUsers::Store(store) => store.get_users(after, limit).await,
+            }
+        })
+    }
+
+    fn skip_users(&self, offset: i64) -> BoxFuture<'_, Result<Option<UserCursor>, Error>> {
+        Box::pin(async move {
+            match &self.users {
+                DryRunUsers::List(users) => Ok(usize::try_from(offset - 1)
+                    .ok()
+                    .and_then(|index| users.get(index))
+                    .map(UserCursor::from)),
+                DryRunUsers::Store(store) => store.skip_users(offset).await,
+            }
+        })
+    }
+
+    fn count_users(&self) -> BoxFuture<'_, Result<i64, Error>> {
+        Box::pin(async move {
+            match &self.users {
+                DryRunUsers::List(users) => Ok(users.len() as i64),
+                DryRunUsers::Store(store) => store.count_users().await,
+            }
+        })
+    }
+
+    fn activity_exists<'a>(&'a self, _activity_id: &'a str) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async { Ok(false) })
+    }
+
+    fn create_activities(
+        &self,
+        activities: Vec<StravaActivity>,
+    ) -> BoxFuture<'_, Result<usize, Error>> {
+        Box::pin(async move { self.write(activities) })
+    }
+
+    fn replace_activities(
+        &self,
+        activities: Vec<StravaActivity>,
+    ) -> BoxFuture<'_, Result<usize, Error>> {
+        Box::pin(async move { self.write(activities) })
+    }
+
+    fn enqueue_jobs<'a>(
+        &'a self,
+        run_id: &'a str,
+        users: Vec<User>,
+    ) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move {
+            let mut queue = self.jobs.lock().map_err(|_| poisoned())?;
+            let mut queued = 0;
+
+            for user in users {
+                if !queue
+                    .queued
+                    .insert((run_id.to_owned(), user.strava_id.clone()))
+                {
+                    continue;
+                }
+
+                let id = queue.jobs.len() as i64 + 1;
+                queue.jobs.push((
+                    ScrapeJob {
+                        id,
+                        run_id: run_id.to_owned(),
+                        user_id: user.id,
+                        strava_id: user.strava_id,
+                        leased_by: String::new(),
+                        attempts: 0,
+                    },
+                    JobStatus::Pending,
+                ));
+                queued += 1;
+            }
+
+            Ok(queued)
+        })
+    }
+
+    fn lease_jobs<'a>(
+        &'a self,
+        run_id: &'a str,
+        worker: &'a str,
+        count: i64,
+        _lease: Duration,
+    ) -> BoxFuture<'a, Result<Vec<ScrapeJob>, Error>> {
+        Box::pin(async move {
+            let mut queue = self.jobs.lock().map_err(|_| poisoned())?;
+
+            Ok(queue
+                .jobs
+                .iter_mut()
+                .filter(|(job, status)| job.run_id == run_id && *status == JobStatus::Pending)
+                .take(count.max(0) as usize)
+                .map(|(job, status)| {
+                    *status = JobStatus::Leased;
+                    job.leased_by = worker.to_owned();
+                    job.attempts += 1;
+                    job.clone()
+                })
+                .collect())
+        })
+    }
+
+    fn extend_lease<'a>(
+        &'a self,
+        _job: &'a ScrapeJob,
+        _lease: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        // Jobs of a dry run only live in this process, their lease never
+        // expires.
+        Box::pin(async move { Ok(true) })
+    }
+
+    fn complete_job<'a>(&'a self, job: &'a ScrapeJob) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move { self.set_status(job, JobStatus::Done) })
+    }
+
+    fn fail_job<'a>(
+        &'a self,
+        job: &'a ScrapeJob,
+        _reason: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move { self.set_status(job, JobStatus::Failed) })
+    }
+
+    fn release_job<'a>(&'a self, job: &'a ScrapeJob) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move { self.set_status(job, JobStatus::Pending) })
+    }
+
+    fn job_counts<'a>(&'a self, run_id: &'a str) -> BoxFuture<'a, Result<JobCounts, Error>> {
+        Box::pin(async move {
+            let queue = self.jobs.lock().map_err(|_| poisoned())?;
+            let count = |status: JobStatus| {
+                queue
+                    .jobs
+                    .iter()
+                    .filter(|(job, current)| job.run_id == run_id && *current == status)
+                    .count() as i64
+            };
+
+            Ok(JobCounts {
+                pending: count(JobStatus::Pending),
+                leased: count(JobStatus::Leased),
+                done: count(JobStatus::Done),
+                failed: count(JobStatus::Failed),
+            })
+        })
+    }
+
+    fn create_logs(&self, logs: Vec<NewScrapLog>) -> BoxFuture<'_, Result<usize, Error>> {
+        Box::pin(async move {
+            for log in &logs {
+                debug!(
+                    "[DRY-RUN][{}][{}] {}",
+                    log.strava_id, log.activity_id, log.status
+                );
+            }
+
+            Ok(logs.len())
+        })
+    }
+
+    fn failures_by_athlete<'a>(
+        &'a self,
+        _run_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<FailureCount>, Error>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    fn failures_by_run(&self) -> BoxFuture<'_, Result<Vec<FailureCount>, Error>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    fn get_cursors<'a>(
+        &'a self,
+        _strava_ids: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<AthleteCursor>, Error>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    fn save_cursor<'a>(&'a self, _cursor: &'a AthleteCursor) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn backfilled_months<'a>(
+        &'a self,
+        _strava_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, Error>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    fn save_backfilled_month<'a>(
+        &'a self,
+        _strava_id: &'a str,
+        _month: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+}
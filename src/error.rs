@@ -75,6 +75,51 @@ pub enum Error {
     /// Forbidden error. This error occurs when the server refuses to authorize
     #[fail(display = "Forbidden: {}", _0)]
     Forbidden(i32, String),
+
+    /// Parse error. This error occurs when a Strava page does not have the
+    /// expected structure.
+    #[fail(display = "Parse error: {}", _0)]
+    Parse(#[cause] ParseError),
+
+    /// Strava kept answering with an unexpected HTTP status after all retries.
+    #[fail(display = "Unexpected HTTP status: {}", _0)]
+    HttpStatus(u16),
+
+    /// The scrapper was shut down before the work was finished.
+    #[fail(display = "Interrupted by shutdown")]
+    Interrupted,
+}
+
+/// List of possible errors while parsing Strava pages.
+#[derive(Fail, Debug)]
+pub enum ParseError {
+    /// The page has no `<body>` element, usually an empty or truncated response.
+    #[fail(display = "Page has no body")]
+    MissingBody,
+
+    /// The page is rendered for anonymous visitor, the session cookie has expired.
+    #[fail(display = "Not logged in")]
+    NotLoggedIn,
+
+    /// The athlete page has no `react-feed-component` with feed data.
+    #[fail(display = "No activity data found")]
+    NoFeed,
+
+    /// The feed data could not be deserialized.
+    #[fail(display = "Invalid activity feed: {}", _0)]
+    InvalidFeed(#[cause] serde_json::Error),
+
+    /// The activity page has no `inline-stats` block.
+    #[fail(display = "No inline stats found")]
+    NoInlineStats,
+
+    /// The `pageView.activity().set(...)` payload could not be deserialized.
+    #[fail(display = "Invalid raw stats: {}", _0)]
+    InvalidRawStats(#[cause] serde_json::Error),
+
+    /// The activity id is not numeric.
+    #[fail(display = "Invalid activity id: {}", _0)]
+    InvalidActivityId(String),
 }
 
 /// Definisi kode kesalahan
@@ -164,6 +209,12 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Error::Parse(value)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
         Error::InternalError(ErrorCode::UnknownError as i32, value.into())
@@ -193,6 +244,12 @@ impl From<r2d2::Error> for Error {
         Error::InternalError(ErrorCode::UnknownError as i32, value.into())
     }
 }
+
+impl From<r2d2::PoolError> for Error {
+    fn from(value: r2d2::PoolError) -> Self {
+        Error::InternalError(ErrorCode::DatabaseError as i32, value.into())
+    }
+}
 // use scraper::error::SelectorErrorKind;
 
 // impl From<SelectorErrorKind<'_>> for Error {
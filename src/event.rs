@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+/// Progress notification emitted by the scrapper.
+#[derive(Debug, Clone)]
+pub enum ScrapeEvent {
+    /// Athlete feed was fetched and parsed.
+    AthleteFetched {
+        job_id: i64,
+        strava_id: String,
+        activities: usize,
+    },
+    /// Athlete was skipped because the feed could not be fetched or parsed.
+    AthleteSkipped {
+        job_id: i64,
+        strava_id: String,
+        reason: String,
+    },
+    /// Activity overview was fetched and parsed.
+    ActivityParsed {
+        job_id: i64,
+        strava_id: String,
+        activity_id: i64,
+    },
+    /// Activity was skipped, e.g. already saved or the overview could not be parsed.
+    ActivitySkipped {
+        job_id: i64,
+        activity_id: String,
+        reason: String,
+    },
+    /// Parsed activities of an athlete were saved.
+    ActivitiesInserted {
+        job_id: i64,
+        strava_id: String,
+        inserted: usize,
+    },
+    /// Too many athletes in a row failed as not logged in, the workers are
+    /// stopping. Raised once per scrapper.
+    SessionExpired { failures: u32 },
+    /// Worker has processed all its athletes.
+    WorkerFinished { job_id: i64 },
+}
+
+/// Callback receiving every [`ScrapeEvent`].
+pub type EventCallback = Arc<dyn Fn(&ScrapeEvent) + Send + Sync>;
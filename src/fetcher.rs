@@ -0,0 +1,262 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{Datelike, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::ClientPool,
+    config::HttpConfig,
+    error::Error,
+    rate_limit::RateLimiter,
+    session::{CookieFile, SessionSource},
+};
+
+/// Raw HTTP response as seen by the scrapper.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FetchResponse {
+    pub url: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl FetchResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Lookup a header value by name (case insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Source of Strava pages used by the scrapper.
+///
+/// `interval` is the feed month formatted as `YYYYMM`.
+pub trait Fetcher: Send + Sync {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>>;
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>>;
+
+    /// Hold back every request of the fetcher for `duration`, after Strava
+    /// asked to wait with `Retry-After`. Ignored by fetchers without a rate
+    /// limit.
+    fn pause(&self, _duration: Duration) {}
+}
+
+/// Fetch pages from strava.com, or the configured base URL, using a logged in
+/// session.
+#[derive(Clone)]
+pub struct HttpFetcher {
+    config: HttpConfig,
+    sessions: Arc<dyn SessionSource>,
+    clients: Arc<ClientPool>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        Self::new(HttpConfig::default())
+    }
+}
+
+impl HttpFetcher {
+    /// Create fetcher using the sessions from the configured cookie file.
+    pub fn new(config: HttpConfig) -> Self {
+        let sessions = Arc::new(CookieFile::new(&config.cookies_path));
+
+        Self::with_sessions(config, sessions)
+    }
+
+    /// Create fetcher using the given sessions, limited to
+    /// `config.requests_per_sec`. Clones share the clients and the limit.
+    pub fn with_sessions(mut config: HttpConfig, sessions: Arc<dyn SessionSource>) -> Self {
+        config.base_url = config.base_url.trim_end_matches('/').to_string();
+        let limiter = Arc::new(RateLimiter::new(config.requests_per_sec, config.burst));
+        let clients = Arc::new(ClientPool::new(config.clone()));
+
+        Self {
+            config,
+            sessions,
+            clients,
+            limiter,
+        }
+    }
+
+    /// Share the rate limit with other fetchers.
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub fn limiter(&self) -> Arc<RateLimiter> {
+        Arc::clone(&self.limiter)
+    }
+
+    async fn get(&self, url: String) -> Result<FetchResponse, Error> {
+        let session = self.sessions.session(None)?;
+        let pooled = self.clients.client(&session).await?;
+        self.limiter.acquire().await;
+        let res = match pooled.client.get(&url).send().await {
+            Ok(res) => res,
+            Err(e) => {
+                self.clients.evict(&session, pooled.proxy.as_deref());
+                return Err(e.into());
+            }
+        };
+
+        let status = res.status().as_u16();
+        let headers = res
+            .headers()
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+        let body = res.text().await?;
+
+        Ok(FetchResponse {
+            url,
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Fetch the profile page of an athlete, without the feed parameters.
+    pub async fn fetch_athlete_page(&self, athlete_id: &str) -> Result<FetchResponse, Error> {
+        self.get(format!(
+            "{base_url}/athletes/{athlete_id}",
+            base_url = self.config.base_url
+        ))
+        .await
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        // The feed chart shows one year, older months need it shifted back.
+        let year_offset = interval
+            .get(..4)
+            .and_then(|year| year.parse::<i32>().ok())
+            .map_or(0, |year| (Utc::now().year() - year).max(0));
+
+        Box::pin(self.get(format!(
+            "{base_url}/athletes/{athlete_id}?chart_type=miles&interval_type=month&interval={interval}&year_offset={year_offset}",
+            base_url = self.config.base_url
+        )))
+    }
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        Box::pin(self.get(format!(
+            "{base_url}/activities/{activity_id}/overview",
+            base_url = self.config.base_url
+        )))
+    }
+
+    fn pause(&self, duration: Duration) {
+        self.limiter.pause(duration);
+    }
+}
+
+/// Serve saved HTML pages from a directory, useful to run the scrapper offline.
+///
+/// Expected layout:
+///
+/// ```text
+/// <root>/athletes/<athlete_id>-<interval>.html   (or <athlete_id>.html)
+/// <root>/activities/<activity_id>.html
+/// ```
+///
+/// Missing files are answered with a `404` response. Served pages carry the
+/// `status` header of strava.com, without which the scrapper rejects an
+/// athlete page as not coming from Strava.
+#[derive(Clone)]
+pub struct DirFetcher {
+    root: PathBuf,
+}
+
+impl DirFetcher {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn serve(&self, candidates: &[PathBuf]) -> Result<FetchResponse, Error> {
+        for path in candidates {
+            if path.is_file() {
+                return Ok(FetchResponse {
+                    url: path.display().to_string(),
+                    status: 200,
+                    headers: vec![("status".to_string(), "200 OK".to_string())],
+                    body: fs::read_to_string(path)?,
+                });
+            }
+        }
+
+        Ok(FetchResponse {
+            url: candidates
+                .first()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            status: 404,
+            ..Default::default()
+        })
+    }
+}
+
+impl Fetcher for DirFetcher {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        let dir = self.root.join("athletes");
+        let res = self.serve(&[
+            dir.join(format!("{athlete_id}-{interval}.html")),
+            dir.join(format!("{athlete_id}.html")),
+        ]);
+
+        Box::pin(async move { res })
+    }
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        let res = self.serve(&[self
+            .root
+            .join("activities")
+            .join(format!("{activity_id}.html"))]);
+
+        Box::pin(async move { res })
+    }
+}
@@ -4,17 +4,77 @@ extern crate log;
 extern crate serde_json;
 #[macro_use]
 extern crate failure;
-#[macro_use]
-extern crate lazy_static;
 
+mod breaker;
+mod cassette;
+mod checkpoint;
+mod client;
+mod config;
+mod daemon;
+#[cfg(feature = "mysql")]
 mod database;
+mod dry_run;
+mod event;
+mod fetcher;
+#[cfg(feature = "mock")]
+mod mock;
 #[macro_use]
 pub mod error;
 mod models;
+mod parser;
+#[cfg(any(feature = "mysql", feature = "sqlite"))]
+#[macro_use]
+mod queries;
+mod rate_limit;
+#[cfg(feature = "mysql")]
 mod repository;
+mod retry;
+#[cfg(feature = "mysql")]
 mod schema;
 mod scrapper;
+mod session;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
 mod types;
 mod utils;
 
-pub use {database::establish_connection, error::Error, scrapper::Scrapper, types::*, utils::*};
+pub use {
+    breaker::{CircuitBreaker, is_auth_failure},
+    cassette::{RecordingFetcher, ReplayFetcher},
+    checkpoint::Checkpoint,
+    client::{ClientPool, PooledClient},
+    config::{
+        Config, DEFAULT_CONFIG_PATH, DEFAULT_SPORTS, DaemonConfig, DatabaseConfig, HttpConfig,
+        ScrapperConfig,
+    },
+    daemon::{Daemon, DaemonState, Schedule},
+    dry_run::{DryRunStore, DryRunUsers},
+    error::{Error, ParseError},
+    event::{EventCallback, ScrapeEvent},
+    fetcher::{DirFetcher, FetchResponse, Fetcher, HttpFetcher},
+    models::{
+        AthleteCursor, FailureCount, JobCounts, JobStatus, NewScrapLog, ScrapStatus, ScrapeJob,
+        User, UserCursor,
+    },
+    parser::{
+        has_inline_stats, is_logged_in, parse_activity_overview, parse_athlete_feed,
+        parse_raw_stats,
+    },
+    rate_limit::RateLimiter,
+    retry::{Clock, RetryPolicy, SystemClock, is_retryable_error, is_retryable_status},
+    scrapper::{Scrapper, ScrapperBuilder},
+    session::{CookieFile, SessionSource, StaticSessions},
+    store::{ActivityStore, connect, connect_read_only},
+    types::*,
+    utils::*,
+};
+
+#[cfg(feature = "mysql")]
+pub use {database::establish_connection, repository::Repository};
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteRepository;
+
+#[cfg(feature = "mock")]
+pub use mock::{MockResponse, MockStrava};
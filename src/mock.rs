@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::error::Error;
+
+const LOGGED_OUT_PAGE: &str =
+    r#"<html><body class="logged-out"><div class="login">Log In</div></body></html>"#;
+const NO_INLINE_STATS_PAGE: &str =
+    r#"<html><body class="logged-in"><div class="activity-summary"></div></body></html>"#;
+
+/// Canned answer of the mock server for a path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockResponse {
+    /// Serve the fixture file, `404` when the file does not exist.
+    Fixture,
+    /// Serve a page rendered for anonymous visitor.
+    LoggedOut,
+    /// Serve a logged in activity page without the `inline-stats` block.
+    MissingInlineStats,
+    /// Answer with the status code and an empty body, e.g. `429` or `503`.
+    Status(u16),
+}
+
+impl std::str::FromStr for MockResponse {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixture" => Ok(Self::Fixture),
+            "logged-out" => Ok(Self::LoggedOut),
+            "missing-inline-stats" => Ok(Self::MissingInlineStats),
+            code => code
+                .parse()
+                .map(Self::Status)
+                .map_err(|_| format!("Unknown mock response: {code}")),
+        }
+    }
+}
+
+/// Local stand-in for strava.com serving `/athletes/{id}` and
+/// `/activities/{id}/overview` from fixture files.
+///
+/// Fixture layout is the same as [`DirFetcher`](crate::DirFetcher):
+///
+/// ```text
+/// <root>/athletes/<athlete_id>-<interval>.html   (or <athlete_id>.html)
+/// <root>/activities/<activity_id>.html
+/// ```
+pub struct MockStrava {
+    fixtures: PathBuf,
+    scripts: Mutex<HashMap<String, VecDeque<MockResponse>>>,
+    defaults: Mutex<HashMap<String, MockResponse>>,
+    hits: Mutex<HashMap<String, usize>>,
+}
+
+impl MockStrava {
+    pub fn new<P: AsRef<Path>>(fixtures: P) -> Arc<Self> {
+        Arc::new(Self {
+            fixtures: fixtures.as_ref().to_path_buf(),
+            scripts: Mutex::new(HashMap::new()),
+            defaults: Mutex::new(HashMap::new()),
+            hits: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Answer the next requests to `path` (without query string) with the given
+    /// responses in order, then fall back to the default response.
+    pub fn script<I: IntoIterator<Item = MockResponse>>(&self, path: &str, responses: I) {
+        self.scripts
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .extend(responses);
+    }
+
+    /// Always answer requests to `path` with the given response.
+    pub fn respond(&self, path: &str, response: MockResponse) {
+        self.defaults
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), response);
+    }
+
+    /// Number of requests received for `path`.
+    pub fn hits(&self, path: &str) -> usize {
+        self.hits
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Bind to `addr` and serve requests in background, returns the bound
+    /// address. Use port `0` to pick a free port.
+    pub async fn start(self: &Arc<Self>, addr: SocketAddr) -> Result<SocketAddr, Error> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let mock = Arc::clone(self);
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mock = Arc::clone(&mock);
+                tokio::spawn(async move {
+                    if let Err(e) = mock.handle(stream).await {
+                        warn!("[MOCK] Failed to handle request: {e}");
+                    }
+                });
+            }
+        });
+
+        Ok(local_addr)
+    }
+
+    fn next_response(&self, path: &str) -> MockResponse {
+        *self
+            .hits
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default() += 1;
+
+        if let Some(response) = self
+            .scripts
+            .lock()
+            .unwrap()
+            .get_mut(path)
+            .and_then(|queue| queue.pop_front())
+        {
+            return response;
+        }
+
+        self.defaults
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .unwrap_or(MockResponse::Fixture)
+    }
+
+    fn fixture(&self, path: &str, query: &str) -> Option<String> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let candidates = match segments.as_slice() {
+            ["athletes", id] => {
+                let interval = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("interval="))
+                    .unwrap_or_default();
+                let dir = self.fixtures.join("athletes");
+                vec![
+                    dir.join(format!("{id}-{interval}.html")),
+                    dir.join(format!("{id}.html")),
+                ]
+            }
+            ["activities", id, "overview"] => {
+                vec![self.fixtures.join("activities").join(format!("{id}.html"))]
+            }
+            _ => vec![],
+        };
+
+        candidates
+            .into_iter()
+            .find_map(|path| fs::read_to_string(path).ok())
+    }
+
+    async fn handle(&self, mut stream: TcpStream) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let request = String::from_utf8_lossy(&buf);
+        let target = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/");
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        let (status, body) = match self.next_response(path) {
+            MockResponse::Fixture => match self.fixture(path, query) {
+                Some(body) => (200, body),
+                None => (404, String::new()),
+            },
+            MockResponse::LoggedOut => (200, LOGGED_OUT_PAGE.to_string()),
+            MockResponse::MissingInlineStats => (200, NO_INLINE_STATS_PAGE.to_string()),
+            MockResponse::Status(code) => (code, String::new()),
+        };
+
+        debug!("[MOCK] GET {target} -> {status}");
+
+        let mut head = format!(
+            "HTTP/1.1 {status} MOCK\r\nstatus: {status}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n",
+            body.len()
+        );
+        if status == 429 {
+            head.push_str("retry-after: 1\r\n");
+        }
+        head.push_str("\r\n");
+
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+        stream.shutdown().await?;
+
+        Ok(())
+    }
+}
@@ -1,18 +1,50 @@
 #![allow(dead_code)]
+use std::fmt;
+
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ParseError};
+#[cfg(feature = "mysql")]
 use crate::schema::*;
-use diesel::mysql::Mysql;
-use diesel::prelude::*;
+#[cfg(feature = "mysql")]
+use crate::types::SportType;
+#[cfg(feature = "mysql")]
+use diesel::{mysql::Mysql, prelude::*};
 
-#[derive(Queryable, Selectable, Debug, Clone)]
-#[diesel(table_name = users)]
-#[diesel(check_for_backend(Mysql))]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "mysql",
+    derive(Queryable, Selectable),
+    diesel(table_name = users),
+    diesel(check_for_backend(Mysql))
+)]
 pub struct User {
     pub id: u64,
     pub name: String,
     pub email: String,
     pub strava_id: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Position of an athlete in the order of `users`, newest first. Paging
+/// after it keeps the pages stable when athletes sign up during a run.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UserCursor {
+    pub created_at: NaiveDateTime,
+    pub id: u64,
+}
+
+impl From<&User> for UserCursor {
+    fn from(user: &User) -> Self {
+        Self {
+            created_at: user.created_at,
+            id: user.id,
+        }
+    }
 }
 
+#[cfg(feature = "mysql")]
 #[derive(Queryable, Debug, Clone)]
 #[diesel(table_name = strava_activities_filter)]
 #[diesel(check_for_backend(Mysql))]
@@ -35,13 +67,160 @@ pub struct StravaActivityFilter {
     pub activity_date: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Queryable, Debug, Clone)]
+#[cfg(feature = "mysql")]
+impl StravaActivityFilter {
+    /// Activity type of the saved `sport_type`.
+    pub fn sport(&self) -> Option<SportType> {
+        self.sport_type.clone().map(SportType::from)
+    }
+}
+
+#[cfg(feature = "mysql")]
+#[derive(Queryable, Selectable, Debug, Clone)]
 #[diesel(table_name = scrap_logs)]
 #[diesel(check_for_backend(Mysql))]
 pub struct ScrapLog {
-    pub id: i64,
+    pub id: u64,
     pub user_id: u64,
     pub strava_id: String,
+    pub activity_id: String,
     pub status: String,
     pub created_at: chrono::NaiveDateTime,
+    pub run_id: Option<String>,
+}
+
+/// Outcome of an athlete fetch or activity parse, saved in `scrap_logs`.
+#[derive(Debug, Clone)]
+pub struct NewScrapLog {
+    pub run_id: String,
+    pub user_id: u64,
+    pub strava_id: String,
+    /// Empty for the athlete feed itself.
+    pub activity_id: String,
+    pub status: ScrapStatus,
+}
+
+/// Value of `scrap_logs.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapStatus {
+    Ok,
+    NotLoggedIn,
+    NoFeed,
+    ParseError,
+    AlreadyExists,
+    /// Strava answered with this HTTP status after all retries.
+    Http(u16),
+    /// Network, database or any other error.
+    Error,
+}
+
+impl ScrapStatus {
+    /// Statuses that are not failures, every other one is counted by the
+    /// failure queries.
+    pub const SUCCESSES: [&'static str; 2] = ["ok", "already_exists"];
+}
+
+impl fmt::Display for ScrapStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrapStatus::Ok => write!(f, "ok"),
+            ScrapStatus::NotLoggedIn => write!(f, "not_logged_in"),
+            ScrapStatus::NoFeed => write!(f, "no_feed"),
+            ScrapStatus::ParseError => write!(f, "parse_error"),
+            ScrapStatus::AlreadyExists => write!(f, "already_exists"),
+            ScrapStatus::Http(code) => write!(f, "http_{code}"),
+            ScrapStatus::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl From<&Error> for ScrapStatus {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Parse(ParseError::NotLoggedIn) => ScrapStatus::NotLoggedIn,
+            Error::Parse(ParseError::NoFeed) => ScrapStatus::NoFeed,
+            Error::Parse(_) => ScrapStatus::ParseError,
+            Error::HttpStatus(code) => ScrapStatus::Http(*code),
+            Error::NotFound(..) => ScrapStatus::Http(404),
+            Error::AlreadyExists => ScrapStatus::AlreadyExists,
+            _ => ScrapStatus::Error,
+        }
+    }
+}
+
+/// Number of failed outcomes in `scrap_logs` for an athlete or a run.
+#[derive(Debug, Clone)]
+pub struct FailureCount {
+    /// Strava ID of the athlete, or the run id.
+    pub key: String,
+    pub failures: i64,
+}
+
+/// Athlete queued for a scrape run in the `scrape_jobs` table.
+#[derive(Debug, Clone)]
+pub struct ScrapeJob {
+    pub id: i64,
+    pub run_id: String,
+    pub user_id: u64,
+    pub strava_id: String,
+    /// Worker holding the lease.
+    pub leased_by: String,
+    /// Number of times the job was leased, including the current lease.
+    pub attempts: i32,
+}
+
+/// Number of jobs of a run per status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobCounts {
+    pub pending: i64,
+    pub leased: i64,
+    pub done: i64,
+    pub failed: i64,
+}
+
+/// Value of `scrape_jobs.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Pending,
+    Leased,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Leased => "leased",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Scrape progress of an athlete, saved in `athlete_cursors`.
+#[derive(Debug, Clone)]
+pub struct AthleteCursor {
+    pub strava_id: String,
+    pub user_id: u64,
+    pub last_scraped_at: NaiveDateTime,
+    /// Start of the newest activity seen in the athlete feed.
+    pub last_activity_date: Option<NaiveDateTime>,
+}
+
+impl AthleteCursor {
+    /// Whether the athlete should be scraped again at `now`. Athletes without
+    /// recent activity wait longer than `min_interval`: twice as long after a
+    /// week, 4 times after a month and 8 times after 3 months or when no
+    /// activity was ever seen.
+    pub fn is_due(&self, now: NaiveDateTime, min_interval: TimeDelta) -> bool {
+        let factor = match self.last_activity_date.map(|date| now - date) {
+            Some(idle) if idle < TimeDelta::days(7) => 1,
+            Some(idle) if idle < TimeDelta::days(30) => 2,
+            Some(idle) if idle < TimeDelta::days(90) => 4,
+            _ => 8,
+        };
+
+        now - self.last_scraped_at >= min_interval * factor
+    }
 }
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use convert_case::{Case, Casing};
+use regex::Regex;
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Class, Name},
+};
+
+use crate::{
+    error::ParseError,
+    types::{Activity, Props, RawStats, StravaActivity},
+    utils::{elapsed_time_to_sec, leading_number, pace_to_sec, speed_to_kmh, swim_pace_to_sec},
+};
+
+fn body(document: &Document) -> Result<Node<'_>, ParseError> {
+    document
+        .find(Name("body"))
+        .next()
+        .ok_or(ParseError::MissingBody)
+}
+
+/// Check whether the page is rendered for a logged in user.
+pub fn is_logged_in(html: &str) -> bool {
+    let document = Document::from(html);
+
+    body(&document)
+        .map(|body| body.attr("class").unwrap_or_default().contains("logged-in"))
+        .unwrap_or(false)
+}
+
+/// Check whether the activity overview page contains the `inline-stats` block.
+pub fn has_inline_stats(html: &str) -> bool {
+    let document = Document::from(html);
+
+    body(&document)
+        .map(|body| body.find(Class("inline-stats")).next().is_some())
+        .unwrap_or(false)
+}
+
+/// Parse activities of every sport from the athlete page feed.
+pub fn parse_athlete_feed(html: &str) -> Result<Vec<Activity>, ParseError> {
+    let document = Document::from(html);
+    let body = body(&document)?;
+
+    if !body.attr("class").unwrap_or_default().contains("logged-in") {
+        return Err(ParseError::NotLoggedIn);
+    }
+
+    let json_str = body
+        .find(Class("react-feed-component"))
+        .next()
+        .and_then(|el| el.attr("data-react-props"))
+        .ok_or(ParseError::NoFeed)?;
+
+    let props = serde_json::from_str::<Props>(json_str).map_err(ParseError::InvalidFeed)?;
+
+    Ok(props
+        .app_context
+        .entries
+        .into_iter()
+        .filter(|e| ["Activity", "GroupActivity"].contains(&e.entity.as_str()))
+        .filter_map(|e| e.activity)
+        .collect())
+}
+
+/// Parse the `pageView.activity().set({...})` payload of an activity overview page.
+pub fn parse_raw_stats(html: &str) -> Result<RawStats, ParseError> {
+    let re =
+        Regex::new(r#"(?s)pageView\.activity\(\)\.set\(\{\s*(?P<content>.*?)\s*\}\);"#).unwrap();
+
+    if let Some(last) = re.captures_iter(html).last() {
+        let re = Regex::new(r#"(?m)^\s*(\w+)\s*:"#).unwrap();
+        let quoted = re.replace_all(&last["content"], r#""$1":"#);
+
+        let content = format!("{{{}}}", quoted);
+        serde_json::from_str(&content).map_err(ParseError::InvalidRawStats)
+    } else {
+        Ok(RawStats::default())
+    }
+}
+
+/// Collect the `inline-stats` and `more-stats` blocks as `snake_case` label to
+/// value, the max of a `more-stats` row under `max_<label>`.
+fn parse_stats(body: &Node) -> Result<HashMap<String, String>, ParseError> {
+    let mut stats: HashMap<String, String> = HashMap::new();
+
+    let inline_stats = body
+        .find(Class("inline-stats"))
+        .next()
+        .ok_or(ParseError::NoInlineStats)?;
+
+    for li in inline_stats.find(Name("li")) {
+        let (Some(label), Some(value)) = (
+            li.find(Class("label")).next(),
+            li.find(Name("strong")).next(),
+        ) else {
+            continue;
+        };
+
+        stats.insert(
+            label.text().trim().to_case(Case::Snake),
+            value.text().trim().replace(" ", ""),
+        );
+    }
+
+    if let Some(more_stats) = body.find(Class("more-stats")).next() {
+        for div in more_stats.find(Class("row")) {
+            let Some(label) = div
+                .find(Class("spans5"))
+                .next()
+                .map(|label| label.text().trim().to_case(Case::Snake))
+                .filter(|label| !label.is_empty())
+            else {
+                continue;
+            };
+            let mut values = div
+                .find(Class("spans3"))
+                .map(|value| value.text().trim().replace(" ", ""));
+
+            let Some(value) = values.next() else {
+                continue;
+            };
+            stats.insert(label.clone(), value);
+
+            // rows of rides and swims give the average then the max
+            if let Some(max) = values.next().filter(|max| !max.is_empty()) {
+                stats.insert(format!("max_{label}"), max);
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Parse the activity overview page of the given feed activity.
+pub fn parse_activity_overview(
+    html: &str,
+    activity: &Activity,
+) -> Result<StravaActivity, ParseError> {
+    let document = Document::from(html);
+    let body = body(&document)?;
+
+    let activity_id = activity
+        .id
+        .parse()
+        .map_err(|_| ParseError::InvalidActivityId(activity.id.clone()))?;
+    let raw_stats = parse_raw_stats(html)?;
+    let stats = parse_stats(&body)?;
+    let stat = |label: &str| stats.get(label).map(String::as_str);
+    let watts = |raw: Option<f64>, label: &str| {
+        raw.or_else(|| stat(label).and_then(leading_number))
+            .map(|f| f.round() as i32)
+    };
+    let is_swim = activity.activity_type.is_swimming();
+
+    Ok(StravaActivity {
+        activity_id,
+        strava_id: activity.athlete.athlete_id.to_owned(),
+        distance_m: raw_stats.distance.map(|f| f.round() as i32),
+        elev_gain_m: raw_stats.elev_gain.map(|f| f.round() as i32),
+        moving_time_s: raw_stats.moving_time.map(|f| f as i32),
+        elapsed_time_s: stats
+            .get("elapsed_time")
+            .and_then(|s| elapsed_time_to_sec(s)),
+        pace_sec_per_km: stat("pace").filter(|_| !is_swim).and_then(pace_to_sec),
+        pace_text: stats.get("pace").map(|s| s.to_string()),
+        calories: raw_stats.calories.map(|f| f.round() as f32),
+        avg_cadence: raw_stats.avg_cadence.map(|f| f.round() as f32),
+        trainer: raw_stats.trainer,
+        sport_type: Some(activity.activity_type.clone()),
+        avg_speed_kmh: raw_stats
+            .avg_speed
+            .map(|f| (f * 3.6) as f32)
+            .or_else(|| stat("speed").and_then(speed_to_kmh)),
+        max_speed_kmh: raw_stats
+            .max_speed
+            .map(|f| (f * 3.6) as f32)
+            .or_else(|| stat("max_speed").and_then(speed_to_kmh)),
+        avg_power_w: watts(raw_stats.avg_watts, "power"),
+        weighted_power_w: watts(raw_stats.weighted_avg_watts, "weighted_avg_power"),
+        max_power_w: watts(raw_stats.max_watts, "max_power"),
+        swim_pace_sec_per_100m: stat("pace").filter(|_| is_swim).and_then(swim_pace_to_sec),
+        pool_length_m: raw_stats.pool_length.map(|f| f as f32).or_else(|| {
+            stat("pool_length")
+                .and_then(leading_number)
+                .map(|f| f as f32)
+        }),
+        avg_hr: raw_stats.avg_hr.map(|f| f as f32),
+        avg_temp: raw_stats.avg_temp.map(|f| f as f32),
+        use_timer_time: raw_stats.use_timer_time,
+        workout_type: raw_stats.workout_type.map(|code| code as i16),
+        athlete_name: Some(activity.athlete.athlete_name.to_owned()),
+        payload: serde_json::to_string(&json!({
+            "activity": activity,
+            "stats": stats,
+            "raw_stats": raw_stats
+        }))
+        .ok(),
+        activity_date: activity.start_date.to_owned(),
+        scraped_at: Utc::now().naive_utc(),
+    })
+}
@@ -0,0 +1,653 @@
+//! Queries shared by the diesel backends of [`ActivityStore`].
+//!
+//! [`ActivityStore`]: crate::store::ActivityStore
+
+/// Implement [`ActivityStore`](crate::store::ActivityStore) for a repository
+/// with a `conn()` method, over the tables of the `schema` module in scope.
+///
+/// `id` is the Rust type of the user id columns (`u64` for MySQL's unsigned
+/// `BIGINT`, `i64` for SQLite) and `flag` the one of the boolean columns.
+macro_rules! diesel_store {
+    ($repository:ty, schema = $schema:ident, id = $id:ty, flag = $flag:ty $(,)?) => {
+        const _: () = {
+            use std::time::Duration;
+
+            use chrono::NaiveDateTime;
+            use diesel::prelude::*;
+            use futures::future::BoxFuture;
+
+            use $crate::{
+                Error, SportType, StravaActivity,
+                models::{
+                    AthleteCursor, FailureCount, JobCounts, JobStatus, NewScrapLog, ScrapStatus,
+                    ScrapeJob, User, UserCursor,
+                },
+                store::ActivityStore,
+            };
+
+            #[derive(Insertable)]
+            #[diesel(table_name = $schema::strava_activities)]
+            struct CreateActivity {
+                activity_id: i64,
+                strava_id: String,
+                distance_m: Option<i32>,
+                elev_gain_m: Option<i32>,
+                moving_time_s: Option<i32>,
+                elapsed_time_s: Option<i32>,
+                pace_sec_per_km: Option<i16>,
+                pace_text: Option<String>,
+                calories: Option<f32>,
+                avg_cadence: Option<f32>,
+                trainer: Option<$flag>,
+                sport_type: Option<String>,
+                avg_speed_kmh: Option<f32>,
+                max_speed_kmh: Option<f32>,
+                avg_power_w: Option<i32>,
+                weighted_power_w: Option<i32>,
+                max_power_w: Option<i32>,
+                swim_pace_sec_per_100m: Option<i16>,
+                pool_length_m: Option<f32>,
+                avg_hr: Option<f32>,
+                avg_temp: Option<f32>,
+                use_timer_time: Option<$flag>,
+                workout_type: Option<i16>,
+                workout_label: Option<String>,
+                athlete_name: Option<String>,
+                payload: String,
+                scraped_at: chrono::NaiveDateTime,
+                activity_date: Option<String>,
+            }
+
+            impl From<&StravaActivity> for CreateActivity {
+                fn from(a: &StravaActivity) -> Self {
+                    CreateActivity {
+                        activity_id: a.activity_id,
+                        strava_id: a.strava_id.to_owned(),
+                        distance_m: a.distance_m,
+                        elev_gain_m: a.elev_gain_m,
+                        moving_time_s: a.moving_time_s,
+                        elapsed_time_s: a.elapsed_time_s,
+                        pace_sec_per_km: a.pace_sec_per_km,
+                        pace_text: a.pace_text.to_owned(),
+                        calories: a.calories,
+                        avg_cadence: a.avg_cadence,
+                        trainer: a.trainer.map(|a| a.into()),
+                        sport_type: a.sport_type.as_ref().map(SportType::column_value),
+                        avg_speed_kmh: a.avg_speed_kmh,
+                        max_speed_kmh: a.max_speed_kmh,
+                        avg_power_w: a.avg_power_w,
+                        weighted_power_w: a.weighted_power_w,
+                        max_power_w: a.max_power_w,
+                        swim_pace_sec_per_100m: a.swim_pace_sec_per_100m,
+                        pool_length_m: a.pool_length_m,
+                        avg_hr: a.avg_hr,
+                        avg_temp: a.avg_temp,
+                        use_timer_time: a.use_timer_time.map(|a| a.into()),
+                        workout_type: a.workout_type,
+                        workout_label: a.workout().map(|w| w.label().to_string()),
+                        athlete_name: a.athlete_name.to_owned(),
+                        payload: a.payload.clone().unwrap_or_default(),
+                        activity_date: Some(a.activity_date.to_owned()),
+                        scraped_at: a.scraped_at,
+                    }
+                }
+            }
+
+            #[derive(Insertable)]
+            #[diesel(table_name = $schema::scrape_jobs)]
+            struct CreateScrapeJob {
+                run_id: String,
+                user_id: $id,
+                strava_id: String,
+                status: String,
+            }
+
+            #[derive(Insertable)]
+            #[diesel(table_name = $schema::scrap_logs)]
+            struct CreateScrapLog {
+                run_id: Option<String>,
+                user_id: $id,
+                strava_id: String,
+                activity_id: String,
+                status: String,
+            }
+
+            #[derive(Insertable)]
+            #[diesel(table_name = $schema::athlete_cursors)]
+            struct CreateCursor {
+                strava_id: String,
+                user_id: $id,
+                last_scraped_at: NaiveDateTime,
+                last_activity_date: Option<NaiveDateTime>,
+            }
+
+            impl ActivityStore for $repository {
+                fn get_users<'a>(
+                    &'a self,
+                    after: Option<&'a UserCursor>,
+                    limit: i64,
+                ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+                    Box::pin(async move {
+                        use $schema::users;
+                        let conn = &mut self.conn().await;
+
+                        let mut query = users::table
+                            .filter(users::strava_id.ne("-").and(users::strava_id.ne("")))
+                            .into_boxed();
+                        if let Some(after) = after {
+                            query = query.filter(
+                                users::created_at.lt(after.created_at).or(users::created_at
+                                    .eq(after.created_at)
+                                    .and(users::id.lt(after.id as $id))),
+                            );
+                        }
+
+                        let users = query
+                            .select((
+                                users::id,
+                                users::name,
+                                users::email,
+                                users::strava_id,
+                                users::created_at,
+                            ))
+                            .order_by((users::created_at.desc(), users::id.desc()))
+                            .limit(limit)
+                            .load::<($id, String, String, String, NaiveDateTime)>(conn)?
+                            .into_iter()
+                            .map(|(id, name, email, strava_id, created_at)| User {
+                                id: id as u64,
+                                name,
+                                email,
+                                strava_id,
+                                created_at,
+                            })
+                            .collect();
+
+                        Ok(users)
+                    })
+                }
+
+                fn skip_users(
+                    &self,
+                    offset: i64,
+                ) -> BoxFuture<'_, Result<Option<UserCursor>, Error>> {
+                    Box::pin(async move {
+                        use $schema::users;
+                        let conn = &mut self.conn().await;
+
+                        let cursor = users::table
+                            .filter(users::strava_id.ne("-").and(users::strava_id.ne("")))
+                            .select((users::created_at, users::id))
+                            .order_by((users::created_at.desc(), users::id.desc()))
+                            .offset(offset - 1)
+                            .first::<(NaiveDateTime, $id)>(conn)
+                            .optional()?
+                            .map(|(created_at, id)| UserCursor {
+                                created_at,
+                                id: id as u64,
+                            });
+
+                        Ok(cursor)
+                    })
+                }
+
+                fn count_users(&self) -> BoxFuture<'_, Result<i64, Error>> {
+                    Box::pin(async move {
+                        use $schema::users;
+                        let conn = &mut self.conn().await;
+
+                        let total = users::table
+                            .filter(users::strava_id.ne("-").and(users::strava_id.ne("")))
+                            .count()
+                            .get_result::<i64>(conn)?;
+
+                        Ok(total)
+                    })
+                }
+
+                fn activity_exists<'a>(
+                    &'a self,
+                    activity_id: &'a str,
+                ) -> BoxFuture<'a, Result<bool, Error>> {
+                    Box::pin(async move {
+                        use $schema::strava_activities as activities;
+                        let conn = &mut self.conn().await;
+
+                        let activity_id = activity_id.parse::<i64>().unwrap_or_default();
+                        let exist = activities::table
+                            .filter(activities::activity_id.eq(activity_id))
+                            .count()
+                            .get_result::<i64>(conn)?;
+
+                        Ok(exist > 0)
+                    })
+                }
+
+                fn create_activities(
+                    &self,
+                    activities: Vec<StravaActivity>,
+                ) -> BoxFuture<'_, Result<usize, Error>> {
+                    Box::pin(async move {
+                        use $schema::strava_activities as activities;
+                        let conn = &mut self.conn().await;
+
+                        let res = diesel::insert_or_ignore_into(activities::table)
+                            .values(
+                                activities
+                                    .iter()
+                                    .map(CreateActivity::from)
+                                    .collect::<Vec<_>>(),
+                            )
+                            .execute(conn)?;
+
+                        Ok(res)
+                    })
+                }
+
+                fn replace_activities(
+                    &self,
+                    activities: Vec<StravaActivity>,
+                ) -> BoxFuture<'_, Result<usize, Error>> {
+                    Box::pin(async move {
+                        use $schema::strava_activities as activities;
+                        let conn = &mut self.conn().await;
+
+                        // MySQL counts a replaced row twice, as deleted and inserted,
+                        // every activity is counted once on both backends.
+                        diesel::replace_into(activities::table)
+                            .values(
+                                activities
+                                    .iter()
+                                    .map(CreateActivity::from)
+                                    .collect::<Vec<_>>(),
+                            )
+                            .execute(conn)?;
+
+                        Ok(activities.len())
+                    })
+                }
+
+                fn enqueue_jobs<'a>(
+                    &'a self,
+                    run_id: &'a str,
+                    users: Vec<User>,
+                ) -> BoxFuture<'a, Result<usize, Error>> {
+                    Box::pin(async move {
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        let res = diesel::insert_or_ignore_into(jobs::table)
+                            .values(
+                                users
+                                    .into_iter()
+                                    .map(|u| CreateScrapeJob {
+                                        run_id: run_id.to_owned(),
+                                        user_id: u.id as $id,
+                                        strava_id: u.strava_id,
+                                        status: JobStatus::Pending.as_str().to_owned(),
+                                    })
+                                    .collect::<Vec<_>>(),
+                            )
+                            .execute(conn)?;
+
+                        Ok(res)
+                    })
+                }
+
+                fn lease_jobs<'a>(
+                    &'a self,
+                    run_id: &'a str,
+                    worker: &'a str,
+                    count: i64,
+                    lease: Duration,
+                ) -> BoxFuture<'a, Result<Vec<ScrapeJob>, Error>> {
+                    Box::pin(async move {
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        let now = chrono::Utc::now().naive_utc();
+                        let leased_until = now + lease;
+                        let claimable =
+                            jobs::status.eq(JobStatus::Pending.as_str()).or(jobs::status
+                                .eq(JobStatus::Leased.as_str())
+                                .and(jobs::leased_until.lt(now).assume_not_null()));
+
+                        let candidates = jobs::table
+                            .filter(jobs::run_id.eq(run_id))
+                            .filter(claimable)
+                            .select((jobs::id, jobs::user_id, jobs::strava_id, jobs::attempts))
+                            .order_by(jobs::id.asc())
+                            .limit(count)
+                            .load::<(i64, $id, String, i32)>(conn)?;
+
+                        let mut leased = Vec::with_capacity(candidates.len());
+                        for (id, user_id, strava_id, attempts) in candidates {
+                            // Another worker may have leased the job since it was selected,
+                            // the update only succeeds for the first one.
+                            let updated = diesel::update(jobs::table.find(id).filter(claimable))
+                                .set((
+                                    jobs::status.eq(JobStatus::Leased.as_str()),
+                                    jobs::leased_by.eq(worker),
+                                    jobs::leased_until.eq(leased_until),
+                                    jobs::attempts.eq(jobs::attempts + 1),
+                                    jobs::updated_at.eq(now),
+                                ))
+                                .execute(conn)?;
+
+                            if updated == 1 {
+                                leased.push(ScrapeJob {
+                                    id,
+                                    run_id: run_id.to_owned(),
+                                    user_id: user_id as u64,
+                                    strava_id,
+                                    leased_by: worker.to_owned(),
+                                    attempts: attempts + 1,
+                                });
+                            }
+                        }
+
+                        Ok(leased)
+                    })
+                }
+
+                fn extend_lease<'a>(
+                    &'a self,
+                    job: &'a ScrapeJob,
+                    lease: Duration,
+                ) -> BoxFuture<'a, Result<bool, Error>> {
+                    Box::pin(async move {
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        let now = chrono::Utc::now().naive_utc();
+                        let updated = diesel::update(
+                            jobs::table
+                                .find(job.id)
+                                .filter(jobs::status.eq(JobStatus::Leased.as_str()))
+                                .filter(jobs::leased_by.eq(&job.leased_by)),
+                        )
+                        .set((jobs::leased_until.eq(now + lease), jobs::updated_at.eq(now)))
+                        .execute(conn)?;
+
+                        Ok(updated == 1)
+                    })
+                }
+
+                fn complete_job<'a>(
+                    &'a self,
+                    job: &'a ScrapeJob,
+                ) -> BoxFuture<'a, Result<(), Error>> {
+                    Box::pin(async move {
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        diesel::update(
+                            jobs::table
+                                .find(job.id)
+                                .filter(jobs::leased_by.eq(&job.leased_by)),
+                        )
+                        .set((
+                            jobs::status.eq(JobStatus::Done.as_str()),
+                            jobs::leased_until.eq(None::<chrono::NaiveDateTime>),
+                            jobs::updated_at.eq(chrono::Utc::now().naive_utc()),
+                        ))
+                        .execute(conn)?;
+
+                        Ok(())
+                    })
+                }
+
+                fn fail_job<'a>(
+                    &'a self,
+                    job: &'a ScrapeJob,
+                    reason: &'a str,
+                ) -> BoxFuture<'a, Result<(), Error>> {
+                    Box::pin(async move {
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        diesel::update(
+                            jobs::table
+                                .find(job.id)
+                                .filter(jobs::leased_by.eq(&job.leased_by)),
+                        )
+                        .set((
+                            jobs::status.eq(JobStatus::Failed.as_str()),
+                            jobs::leased_until.eq(None::<chrono::NaiveDateTime>),
+                            jobs::last_error.eq(reason),
+                            jobs::updated_at.eq(chrono::Utc::now().naive_utc()),
+                        ))
+                        .execute(conn)?;
+
+                        Ok(())
+                    })
+                }
+
+                fn release_job<'a>(
+                    &'a self,
+                    job: &'a ScrapeJob,
+                ) -> BoxFuture<'a, Result<(), Error>> {
+                    Box::pin(async move {
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        diesel::update(
+                            jobs::table
+                                .find(job.id)
+                                .filter(jobs::leased_by.eq(&job.leased_by)),
+                        )
+                        .set((
+                            jobs::status.eq(JobStatus::Pending.as_str()),
+                            jobs::leased_by.eq(None::<String>),
+                            jobs::leased_until.eq(None::<chrono::NaiveDateTime>),
+                            jobs::updated_at.eq(chrono::Utc::now().naive_utc()),
+                        ))
+                        .execute(conn)?;
+
+                        Ok(())
+                    })
+                }
+
+                fn job_counts<'a>(
+                    &'a self,
+                    run_id: &'a str,
+                ) -> BoxFuture<'a, Result<JobCounts, Error>> {
+                    Box::pin(async move {
+                        use diesel::dsl::count_star;
+                        use $schema::scrape_jobs as jobs;
+                        let conn = &mut self.conn().await;
+
+                        let rows = jobs::table
+                            .filter(jobs::run_id.eq(run_id))
+                            .group_by(jobs::status)
+                            .select((jobs::status, count_star()))
+                            .load::<(String, i64)>(conn)?;
+
+                        let count = |status: JobStatus| {
+                            rows.iter()
+                                .find(|(s, _)| s == status.as_str())
+                                .map_or(0, |(_, count)| *count)
+                        };
+
+                        Ok(JobCounts {
+                            pending: count(JobStatus::Pending),
+                            leased: count(JobStatus::Leased),
+                            done: count(JobStatus::Done),
+                            failed: count(JobStatus::Failed),
+                        })
+                    })
+                }
+
+                fn create_logs(
+                    &self,
+                    logs: Vec<NewScrapLog>,
+                ) -> BoxFuture<'_, Result<usize, Error>> {
+                    Box::pin(async move {
+                        use $schema::scrap_logs;
+                        let conn = &mut self.conn().await;
+
+                        let res = diesel::insert_into(scrap_logs::table)
+                            .values(
+                                logs.into_iter()
+                                    .map(|l| CreateScrapLog {
+                                        run_id: Some(l.run_id),
+                                        user_id: l.user_id as $id,
+                                        strava_id: l.strava_id,
+                                        activity_id: l.activity_id,
+                                        status: l.status.to_string(),
+                                    })
+                                    .collect::<Vec<_>>(),
+                            )
+                            .execute(conn)?;
+
+                        Ok(res)
+                    })
+                }
+
+                fn failures_by_athlete<'a>(
+                    &'a self,
+                    run_id: Option<&'a str>,
+                ) -> BoxFuture<'a, Result<Vec<FailureCount>, Error>> {
+                    Box::pin(async move {
+                        use diesel::dsl::count_star;
+                        use $schema::scrap_logs;
+                        let conn = &mut self.conn().await;
+
+                        let mut query = scrap_logs::table
+                            .filter(scrap_logs::status.ne_all(ScrapStatus::SUCCESSES))
+                            .group_by(scrap_logs::strava_id)
+                            .select((scrap_logs::strava_id, count_star()))
+                            .order_by(count_star().desc())
+                            .into_boxed();
+                        if let Some(run_id) = run_id {
+                            query = query.filter(scrap_logs::run_id.eq(run_id));
+                        }
+
+                        Ok(query
+                            .load::<(String, i64)>(conn)?
+                            .into_iter()
+                            .map(|(key, failures)| FailureCount { key, failures })
+                            .collect())
+                    })
+                }
+
+                fn failures_by_run(&self) -> BoxFuture<'_, Result<Vec<FailureCount>, Error>> {
+                    Box::pin(async move {
+                        use diesel::dsl::count_star;
+                        use $schema::scrap_logs;
+                        let conn = &mut self.conn().await;
+
+                        Ok(scrap_logs::table
+                            .filter(scrap_logs::status.ne_all(ScrapStatus::SUCCESSES))
+                            .filter(scrap_logs::run_id.is_not_null())
+                            .group_by(scrap_logs::run_id)
+                            .select((scrap_logs::run_id.assume_not_null(), count_star()))
+                            .order_by(count_star().desc())
+                            .load::<(String, i64)>(conn)?
+                            .into_iter()
+                            .map(|(key, failures)| FailureCount { key, failures })
+                            .collect())
+                    })
+                }
+
+                fn get_cursors<'a>(
+                    &'a self,
+                    strava_ids: &'a [String],
+                ) -> BoxFuture<'a, Result<Vec<AthleteCursor>, Error>> {
+                    Box::pin(async move {
+                        use $schema::athlete_cursors as cursors;
+                        let conn = &mut self.conn().await;
+
+                        Ok(cursors::table
+                            .filter(cursors::strava_id.eq_any(strava_ids))
+                            .select((
+                                cursors::strava_id,
+                                cursors::user_id,
+                                cursors::last_scraped_at,
+                                cursors::last_activity_date,
+                            ))
+                            .load::<(String, $id, NaiveDateTime, Option<NaiveDateTime>)>(conn)?
+                            .into_iter()
+                            .map(
+                                |(strava_id, user_id, last_scraped_at, last_activity_date)| {
+                                    AthleteCursor {
+                                        strava_id,
+                                        user_id: user_id as u64,
+                                        last_scraped_at,
+                                        last_activity_date,
+                                    }
+                                },
+                            )
+                            .collect())
+                    })
+                }
+
+                fn save_cursor<'a>(
+                    &'a self,
+                    cursor: &'a AthleteCursor,
+                ) -> BoxFuture<'a, Result<(), Error>> {
+                    Box::pin(async move {
+                        use $schema::athlete_cursors as cursors;
+                        let conn = &mut self.conn().await;
+
+                        conn.transaction(|conn| {
+                            let saved = cursors::table
+                                .find(&cursor.strava_id)
+                                .select(cursors::last_activity_date)
+                                .first::<Option<NaiveDateTime>>(conn)
+                                .optional()?
+                                .flatten();
+                            let values = CreateCursor {
+                                strava_id: cursor.strava_id.clone(),
+                                user_id: cursor.user_id as $id,
+                                last_scraped_at: cursor.last_scraped_at,
+                                last_activity_date: saved.max(cursor.last_activity_date),
+                            };
+
+                            diesel::replace_into(cursors::table)
+                                .values(&values)
+                                .execute(conn)
+                        })?;
+
+                        Ok(())
+                    })
+                }
+
+                fn backfilled_months<'a>(
+                    &'a self,
+                    strava_id: &'a str,
+                ) -> BoxFuture<'a, Result<Vec<String>, Error>> {
+                    Box::pin(async move {
+                        use $schema::athlete_backfills as backfills;
+                        let conn = &mut self.conn().await;
+
+                        Ok(backfills::table
+                            .filter(backfills::strava_id.eq(strava_id))
+                            .select(backfills::month)
+                            .load(conn)?)
+                    })
+                }
+
+                fn save_backfilled_month<'a>(
+                    &'a self,
+                    strava_id: &'a str,
+                    month: &'a str,
+                ) -> BoxFuture<'a, Result<(), Error>> {
+                    Box::pin(async move {
+                        use $schema::athlete_backfills as backfills;
+                        let conn = &mut self.conn().await;
+
+                        diesel::insert_or_ignore_into(backfills::table)
+                            .values((
+                                backfills::strava_id.eq(strava_id),
+                                backfills::month.eq(month),
+                            ))
+                            .execute(conn)?;
+
+                        Ok(())
+                    })
+                }
+            }
+        };
+    };
+}
@@ -0,0 +1,123 @@
+use std::{
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// Token bucket shared by every request of the process. Tokens refill at
+/// `requests_per_sec` up to `burst`, each request takes one and waits for it
+/// when the bucket is empty, in the order the requests came in. The bucket
+/// can be paused, e.g. until the end of a Strava rate limit window. Time is
+/// read from `tokio::time`, so tests run it on a paused clock.
+pub struct RateLimiter {
+    requests_per_sec: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Tokens left, negative when requests are already waiting for the next
+    /// ones.
+    tokens: f64,
+    refilled_at: Instant,
+    /// No token is given before this instant.
+    resumes_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_sec: f64, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+
+        Self {
+            requests_per_sec,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+                resumes_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.lock();
+            let now = Instant::now();
+            // Tokens of a paused bucket are counted from its resume.
+            let start = now.max(bucket.resumes_at);
+            let elapsed = start.duration_since(bucket.refilled_at).as_secs_f64();
+
+            bucket.tokens = (bucket.tokens + elapsed * self.requests_per_sec).min(self.burst);
+            bucket.refilled_at = start;
+            bucket.tokens -= 1.0;
+
+            let paused = start - now;
+            if bucket.tokens >= 0.0 {
+                if paused.is_zero() {
+                    return;
+                }
+                paused
+            } else {
+                paused + Duration::from_secs_f64(-bucket.tokens / self.requests_per_sec)
+            }
+        };
+
+        tokio::time::sleep(wait).await;
+    }
+
+    /// Hold back every request for `duration` from now, or longer when the
+    /// bucket is already paused.
+    pub fn pause(&self, duration: Duration) {
+        let mut bucket = self.lock();
+        let resumes_at = Instant::now() + duration;
+
+        bucket.resumes_at = bucket.resumes_at.max(resumes_at);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Bucket> {
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Instants, from the start, at which `count` requests get through.
+    async fn acquire_all(limiter: &RateLimiter, count: usize) -> Vec<Duration> {
+        let start = Instant::now();
+        let mut sent = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            limiter.acquire().await;
+            sent.push(start.elapsed());
+        }
+
+        sent
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_goes_through_then_requests_are_spaced() {
+        let limiter = RateLimiter::new(2.0, 2);
+
+        assert_eq!(
+            acquire_all(&limiter, 5).await,
+            [0, 0, 500, 1000, 1500].map(Duration::from_millis)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_holds_back_every_request() {
+        let limiter = RateLimiter::new(2.0, 2);
+        limiter.pause(Duration::from_secs(10));
+        // A shorter pause does not resume the bucket sooner.
+        limiter.pause(Duration::from_secs(1));
+
+        assert_eq!(
+            acquire_all(&limiter, 3).await,
+            [10_000, 10_000, 10_500].map(Duration::from_millis)
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+
+use crate::{config::ScrapperConfig, error::Error, fetcher::FetchResponse};
+
+/// Source of the current time and of the pauses between attempts, replaced by
+/// a fake clock to check the retries without waiting.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()>;
+}
+
+/// [`Clock`] of the system, pausing with `tokio::time::sleep`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// How many times a request is attempted and how long to wait in between.
+///
+/// The pause doubles after each failed attempt, from `base_delay` up to
+/// `max_delay`, and is picked at random in its upper half so workers failing
+/// together do not retry together. A `Retry-After` given by the server is
+/// waited instead, unless it is longer than `max_retry_after`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub max_retry_after: Duration,
+    /// Pick each pause at random in the upper half of the backoff, off to
+    /// get the same pauses on every run.
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Policy of the athlete pages.
+    pub fn athlete(config: &ScrapperConfig) -> Self {
+        Self::with_attempts(config, config.athlete_retries)
+    }
+
+    /// Policy of the activity overviews.
+    pub fn activity(config: &ScrapperConfig) -> Self {
+        Self::with_attempts(config, config.activity_retries)
+    }
+
+    fn with_attempts(config: &ScrapperConfig, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+            max_delay: Duration::from_millis(config.retry_max_delay_ms),
+            max_retry_after: Duration::from_millis(config.retry_after_max_ms),
+            jitter: true,
+        }
+    }
+
+    /// Longest pause after the failed `attempt` (1 for the first one),
+    /// before jitter.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Pause before the attempt following the failed `attempt`, `None` when
+    /// the request should be given up.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_retry_after => None,
+            Some(retry_after) => Some(retry_after),
+            None if self.jitter => {
+                Some(self.backoff(attempt).mul_f64(rand::random_range(0.5..=1.0)))
+            }
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Whether a response with this status may succeed when sent again: rate
+/// limiting, timeouts and server errors. Other client errors such as `403` or
+/// `404` will not change.
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429 | 500..=599)
+}
+
+/// Whether the request failed before getting a response because of a timeout
+/// or a connection failure.
+pub fn is_retryable_error(error: &Error) -> bool {
+    match error {
+        Error::InternalError(_, cause) => cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_timeout() || e.is_connect()),
+        _ => false,
+    }
+}
+
+/// Pause asked by the `Retry-After` header, given in seconds or as an HTTP
+/// date.
+pub fn retry_after(res: &FetchResponse, now: DateTime<Utc>) -> Option<Duration> {
+    let value = res.header("retry-after")?.trim();
+
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => DateTime::parse_from_rfc2822(value).ok().map(|date| {
+            (date.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or_default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Clock standing still until slept on, recording every pause.
+    struct FakeClock {
+        now: Mutex<DateTime<Utc>>,
+        slept: Mutex<Vec<Duration>>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self {
+                now: Mutex::new(Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap()),
+                slept: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn slept(&self) -> Vec<Duration> {
+            self.slept.lock().unwrap().clone()
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+            *self.now.lock().unwrap() += duration;
+            self.slept.lock().unwrap().push(duration);
+            Box::pin(async {})
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(3),
+            max_retry_after: Duration::from_secs(900),
+            jitter: false,
+        }
+    }
+
+    fn response(status: u16, retry_after: Option<&str>) -> FetchResponse {
+        FetchResponse {
+            status,
+            headers: retry_after
+                .map(|value| vec![("Retry-After".to_string(), value.to_string())])
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Send `responses` in order the way the scrapper does, returning the
+    /// number of attempts and whether the last one succeeded.
+    async fn attempt_all(
+        policy: &RetryPolicy,
+        clock: &FakeClock,
+        responses: &[FetchResponse],
+    ) -> (u32, bool) {
+        let mut attempt = 0;
+
+        for res in responses {
+            attempt += 1;
+            if res.is_success() {
+                return (attempt, true);
+            }
+            if !is_retryable_status(res.status) {
+                return (attempt, false);
+            }
+
+            match policy.delay(attempt, retry_after(res, clock.now())) {
+                Some(delay) => clock.sleep(delay).await,
+                None => return (attempt, false),
+            }
+        }
+
+        (attempt, false)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_delay() {
+        let policy = policy();
+        let backoffs = (1..=5).map(|attempt| policy.backoff(attempt));
+
+        assert_eq!(
+            backoffs.collect::<Vec<_>>(),
+            [500, 1000, 2000, 3000, 3000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn delay_is_the_backoff_without_jitter() {
+        let policy = policy();
+
+        for attempt in 1..policy.max_attempts {
+            assert_eq!(policy.delay(attempt, None), Some(policy.backoff(attempt)));
+        }
+    }
+
+    #[test]
+    fn jitter_stays_in_the_upper_half_of_the_backoff() {
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy()
+        };
+
+        for _ in 0..100 {
+            let delay = policy.delay(3, None).unwrap();
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let policy = policy();
+
+        assert!(policy.delay(policy.max_attempts - 1, None).is_some());
+        assert_eq!(policy.delay(policy.max_attempts, None), None);
+        assert_eq!(
+            policy.delay(policy.max_attempts, Some(Duration::from_secs(1))),
+            None
+        );
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_dates() {
+        let clock = FakeClock::new();
+        let date = (clock.now() + Duration::from_secs(90)).to_rfc2822();
+
+        assert_eq!(
+            retry_after(&response(429, Some("120")), clock.now()),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(&response(429, Some(&date)), clock.now()),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(retry_after(&response(429, None), clock.now()), None);
+        assert_eq!(retry_after(&response(429, Some("soon")), clock.now()), None);
+    }
+
+    #[test]
+    fn retry_after_is_waited_up_to_max_retry_after() {
+        let policy = policy();
+
+        // Longer than `max_delay`, as long as a Strava rate limit window.
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(900))),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(901))), None);
+    }
+
+    #[tokio::test]
+    async fn retries_with_backoff_until_success() {
+        let (policy, clock) = (policy(), FakeClock::new());
+        let responses = [
+            response(503, None),
+            response(500, None),
+            response(408, None),
+            response(200, None),
+        ];
+
+        assert_eq!(attempt_all(&policy, &clock, &responses).await, (4, true));
+        assert_eq!(clock.slept(), [500, 1000, 2000].map(Duration::from_millis));
+    }
+
+    #[tokio::test]
+    async fn waits_the_retry_after_of_the_server() {
+        let (policy, clock) = (policy(), FakeClock::new());
+        let date = (clock.now() + Duration::from_secs(600)).to_rfc2822();
+        let responses = [
+            response(429, Some("60")),
+            response(429, Some(&date)),
+            response(200, None),
+        ];
+
+        assert_eq!(attempt_all(&policy, &clock, &responses).await, (3, true));
+        // The date is reached 60 seconds sooner after the first pause.
+        assert_eq!(clock.slept(), [60, 540].map(Duration::from_secs));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_retry_after_too_long() {
+        let (policy, clock) = (policy(), FakeClock::new());
+        let responses = [response(429, Some("3600")), response(200, None)];
+
+        assert_eq!(attempt_all(&policy, &clock, &responses).await, (1, false));
+        assert!(clock.slept().is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_attempts_run_out() {
+        let (policy, clock) = (policy(), FakeClock::new());
+        let responses = vec![response(503, None); 10];
+
+        assert_eq!(attempt_all(&policy, &clock, &responses).await, (5, false));
+        assert_eq!(clock.slept().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let (policy, clock) = (policy(), FakeClock::new());
+        let responses = [response(404, None), response(200, None)];
+
+        assert_eq!(attempt_all(&policy, &clock, &responses).await, (1, false));
+        assert!(clock.slept().is_empty());
+    }
+}
@@ -24,6 +24,18 @@ diesel::table! {
         avg_cadence     -> Nullable<Float>,
         trainer         -> Nullable<TinyInt>,
         sport_type      -> Nullable<Varchar>,
+        avg_speed_kmh   -> Nullable<Float>,
+        max_speed_kmh   -> Nullable<Float>,
+        avg_power_w     -> Nullable<Integer>,
+        weighted_power_w -> Nullable<Integer>,
+        max_power_w     -> Nullable<Integer>,
+        swim_pace_sec_per_100m -> Nullable<SmallInt>,
+        pool_length_m   -> Nullable<Float>,
+        avg_hr          -> Nullable<Float>,
+        avg_temp        -> Nullable<Float>,
+        use_timer_time  -> Nullable<TinyInt>,
+        workout_type    -> Nullable<SmallInt>,
+        workout_label   -> Nullable<Varchar>,
         athlete_name    -> Nullable<Varchar>,
         payload         -> Text,
         scraped_at      -> Timestamp,
@@ -39,7 +51,48 @@ diesel::table! {
         activity_id -> Varchar,
         status -> Varchar,
         created_at -> Timestamp,
+        run_id -> Nullable<Varchar>,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(users, strava_activities,);
+diesel::table! {
+    scrape_jobs (id) {
+        id -> BigInt,
+        run_id -> Varchar,
+        user_id -> Unsigned<BigInt>,
+        strava_id -> Varchar,
+        status -> Varchar,
+        attempts -> Integer,
+        leased_by -> Nullable<Varchar>,
+        leased_until -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    athlete_cursors (strava_id) {
+        strava_id -> Varchar,
+        user_id -> Unsigned<BigInt>,
+        last_scraped_at -> Datetime,
+        last_activity_date -> Nullable<Datetime>,
+    }
+}
+
+diesel::table! {
+    athlete_backfills (strava_id, month) {
+        strava_id -> Varchar,
+        month -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(
+    users,
+    strava_activities,
+    scrap_logs,
+    scrape_jobs,
+    athlete_cursors,
+    athlete_backfills,
+);
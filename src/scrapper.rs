@@ -1,364 +1,910 @@
 #![allow(dead_code)]
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
 use crate::{
-    database::{self},
-    error::Error,
-    repository::Repository,
-    types::{Activity, Props, RawStats, StravaActivity},
-    utils::{self, elapsed_time_to_sec, pace_to_sec},
+    breaker::{self, CircuitBreaker},
+    config::Config,
+    error::{Error, ParseError},
+    event::{EventCallback, ScrapeEvent},
+    fetcher::{Fetcher, HttpFetcher},
+    models::{AthleteCursor, JobCounts, NewScrapLog, ScrapStatus, ScrapeJob, User, UserCursor},
+    parser,
+    retry::{self, Clock, RetryPolicy, SystemClock},
+    session::{CookieFile, SessionSource},
+    store::{self, ActivityStore},
+    types::{Activity, StravaActivity},
+    utils::{month_interval, months_between, parse_month},
 };
-use chrono::{Datelike, Utc};
-use convert_case::{Case, Casing};
+use chrono::{DateTime, TimeDelta, Utc};
 use futures::stream::{FuturesUnordered, StreamExt};
-use regex::Regex;
-use reqwest::Client;
-use select::{
-    document::Document,
-    predicate::{Class, Name},
-};
-use tokio::task;
+use tokio::{sync::watch, task};
+
+/// Builder of [`Scrapper`], every part not given is created from the config.
+#[derive(Default)]
+pub struct ScrapperBuilder {
+    config: Config,
+    store: Option<Arc<dyn ActivityStore>>,
+    fetcher: Option<Arc<dyn Fetcher>>,
+    sessions: Option<Arc<dyn SessionSource>>,
+    clock: Option<Arc<dyn Clock>>,
+    on_event: Option<EventCallback>,
+}
 
-const BATCH_SIZE: usize = 300;
-const MAX_CONCURRENT_TASKS: usize = 50;
+impl ScrapperBuilder {
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
 
-lazy_static! {
-    static ref REPO: Repository = Repository::new(database::clone());
+    /// Store to read athletes from and save activities into, instead of the
+    /// configured database.
+    pub fn store(mut self, store: Arc<dyn ActivityStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Source of Strava pages, instead of an [`HttpFetcher`] for the
+    /// configured base URL.
+    pub fn fetcher(mut self, fetcher: Arc<dyn Fetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
+    /// Sessions used by the default [`HttpFetcher`], instead of the configured
+    /// cookie file. Ignored when a fetcher is given.
+    pub fn session_source(mut self, sessions: Arc<dyn SessionSource>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Clock pausing between retries, instead of the system one.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.config.scrapper.batch_size = batch_size;
+        self
+    }
+
+    pub fn max_concurrent_tasks(mut self, max_concurrent_tasks: usize) -> Self {
+        self.config.scrapper.max_concurrent_tasks = max_concurrent_tasks;
+        self
+    }
+
+    /// Receive every [`ScrapeEvent`] of the scrapper.
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ScrapeEvent) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn build(self) -> Result<Scrapper, Error> {
+        let store = match self.store {
+            Some(store) => store,
+            None => store::connect(&self.config.database)?,
+        };
+        let fetcher = self.fetcher.unwrap_or_else(|| {
+            let sessions = self
+                .sessions
+                .unwrap_or_else(|| Arc::new(CookieFile::new(&self.config.http.cookies_path)));
+
+            Arc::new(HttpFetcher::with_sessions(
+                self.config.http.clone(),
+                sessions,
+            ))
+        });
+        let breaker = CircuitBreaker::new(self.config.scrapper.max_auth_failures);
+
+        Ok(Scrapper {
+            config: Arc::new(self.config),
+            fetcher,
+            store,
+            clock: self.clock.unwrap_or_else(|| Arc::new(SystemClock)),
+            breaker: Arc::new(breaker),
+            auth_failures: Arc::new(Mutex::new(Vec::new())),
+            on_event: self.on_event,
+            shutdown: Arc::new(watch::Sender::new(false)),
+        })
+    }
 }
 
-#[derive(Default, Clone)]
-pub struct Scrapper;
+#[derive(Clone)]
+pub struct Scrapper {
+    config: Arc<Config>,
+    fetcher: Arc<dyn Fetcher>,
+    store: Arc<dyn ActivityStore>,
+    clock: Arc<dyn Clock>,
+    /// Stops every worker once the session looks expired.
+    breaker: Arc<CircuitBreaker>,
+    /// Jobs failed as not logged in since the last athlete scraped.
+    auth_failures: Arc<Mutex<Vec<ScrapeJob>>>,
+    on_event: Option<EventCallback>,
+    /// Set once [`Scrapper::shutdown`] is called, shared by every clone.
+    shutdown: Arc<watch::Sender<bool>>,
+}
 
 impl Scrapper {
-    pub async fn run_worker(
-        &self,
-        arg_offset: i64,
-        arg_limit: Option<i64>,
-        jobs_count: Option<i64>,
-        job_id: i64,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut repo = REPO.clone();
-        let mut futures = FuturesUnordered::new();
+    pub fn builder() -> ScrapperBuilder {
+        ScrapperBuilder::default()
+    }
 
-        if arg_offset < 0 {
-            error!("Offset is negative");
-            return Ok(());
-        }
+    /// Stop taking new athletes. Athletes being scraped are finished, then
+    /// the workers return.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
 
-        let (_, total_records) = repo.get_users(1, 0).await?;
+    pub fn is_shutdown(&self) -> bool {
+        *self.shutdown.borrow()
+    }
 
-        let (arg_offset, mut max_offset) = if let Some(count) = jobs_count {
-            let limit = total_records / count;
-            let mut offset = job_id * limit;
-            let mut max_offset = offset + limit;
+    /// Whether the run was stopped because the Strava session expired.
+    pub fn is_session_expired(&self) -> bool {
+        self.breaker.is_open()
+    }
 
-            if offset > 0 {
-                offset += 1
-            }
+    /// Wait until [`Scrapper::shutdown`] is called.
+    pub async fn wait_shutdown(&self) {
+        self.shutdown.subscribe().wait_for(|stop| *stop).await.ok();
+    }
 
-            if job_id == count - 1 {
-                max_offset = total_records;
+    fn emit(&self, event: ScrapeEvent) {
+        if let Some(callback) = &self.on_event {
+            callback(&event);
+        }
+    }
+
+    /// Queue the athletes of this shard in `offset..offset + limit` for the
+    /// run. Athletes already queued are skipped, so processes sharing a run id
+    /// can all call it. Athletes signing up while queueing are left for the
+    /// next run.
+    pub async fn enqueue(
+        &self,
+        run_id: &str,
+        offset: i64,
+        limit: Option<i64>,
+    ) -> Result<usize, Error> {
+        let batch_size = self.config.scrapper.batch_size as i64;
+        let total = self.store.count_users().await?;
+        let mut after = None;
+        let mut remaining = limit;
+        let mut queued = 0;
+
+        if offset > 0 {
+            match self.store.skip_users(offset).await? {
+                Some(cursor) => after = Some(cursor),
+                None => {
+                    info!("[RUN-{run_id}] No athletes after offset {offset} of {total}");
+                    return Ok(0);
+                }
             }
+        }
 
-            (offset, max_offset)
-        } else {
-            (arg_offset, total_records)
-        };
+        loop {
+            let count = match remaining {
+                Some(remaining) if remaining <= 0 => break,
+                Some(remaining) => batch_size.min(remaining),
+                None => batch_size,
+            };
+
+            let users = self.store.get_users(after.as_ref(), count).await?;
+            let Some(last) = users.last() else {
+                break;
+            };
 
-        if let Some(limit) = arg_limit {
-            max_offset = std::cmp::min(max_offset, arg_offset + limit);
+            after = Some(UserCursor::from(last));
+            remaining = remaining.map(|remaining| remaining - users.len() as i64);
+            let users = users
+                .into_iter()
+                .filter(|user| self.config.scrapper.in_shard(user.id))
+                .collect();
+            let users = self.due_users(users).await?;
+            queued += self.store.enqueue_jobs(run_id, users).await?;
         }
 
-        let batch_size = if max_offset < BATCH_SIZE as i64 {
-            max_offset / 4
-        } else {
-            BATCH_SIZE as i64
-        };
+        info!("[RUN-{run_id}] Queued {queued} of {total} athletes");
 
+        Ok(queued)
+    }
+
+    /// Drop the athletes scraped less than `min_interval_mins` ago, see
+    /// [`AthleteCursor::is_due`].
+    async fn due_users(&self, users: Vec<User>) -> Result<Vec<User>, Error> {
+        let min_interval = TimeDelta::minutes(self.config.scrapper.min_interval_mins as i64);
+        if min_interval.is_zero() {
+            return Ok(users);
+        }
+
+        let strava_ids = users
+            .iter()
+            .map(|u| u.strava_id.clone())
+            .collect::<Vec<_>>();
+        let now = Utc::now().naive_utc();
+        let not_due = self
+            .store
+            .get_cursors(&strava_ids)
+            .await?
+            .into_iter()
+            .filter(|cursor| !cursor.is_due(now, min_interval))
+            .map(|cursor| cursor.strava_id)
+            .collect::<HashSet<_>>();
+
+        if !not_due.is_empty() {
+            debug!("Skipping {} recently scraped athletes", not_due.len());
+        }
+
+        Ok(users
+            .into_iter()
+            .filter(|u| !not_due.contains(&u.strava_id))
+            .collect())
+    }
+
+    /// Count the jobs of the run per status, in the queue of this shard.
+    pub async fn job_counts(&self, run_id: &str) -> Result<JobCounts, Error> {
+        let run_id = self.config.scrapper.shard_run_id(run_id);
+
+        self.store.job_counts(&run_id).await
+    }
+
+    /// Scrape the activities of the configured sports in the feed month
+    /// (`YYYYMM`) of one athlete and save them, returning the number of saved
+    /// activities. With `force`, activities already saved are fetched again
+    /// and replaced.
+    pub async fn scrape_single_athlete(
+        &self,
+        strava_id: &str,
+        interval: &str,
+        force: bool,
+    ) -> Result<usize, Error> {
+        let activities = self
+            .run_scrapper(strava_id, interval, 0)
+            .await?
+            .into_iter()
+            .filter(|a| self.config.scrapper.collects(&a.activity_type))
+            .collect::<Vec<_>>();
         info!(
-            "[JOB-{job_id}] Starting scrapper {arg_offset}/{max_offset} of {total_records} records"
+            "[{strava_id}] Found {} activities to scrape in {interval}",
+            activities.len()
         );
 
-        for offset in (arg_offset..max_offset).step_by(batch_size as usize) {
-            let mut user_repo = repo.clone();
-            let task = task::spawn(async move {
-                let (users, _) = user_repo
-                    .get_users(batch_size, offset)
-                    .await
-                    .expect("Failed to get users");
+        let mut parsed = Vec::with_capacity(activities.len());
+        for activity in &activities {
+            match self.parse_activity(activity, force, 0).await {
+                Ok(activity) => parsed.push(activity),
+                Err(e) => warn!("[{strava_id}][{}] Skipped: {e}", activity.id),
+            }
+        }
 
-                let mut strava_ids = HashMap::new();
+        self.save_activities(parsed, force).await
+    }
 
-                for (_, user) in users.iter().enumerate() {
-                    let id = user.strava_id.clone();
-                    if id.is_empty() || id == "-" {
-                        continue;
-                    }
+    /// Scrape one activity of the athlete, found in the feed month (`YYYYMM`),
+    /// and save it. With `force`, an activity already saved is fetched again
+    /// and replaced.
+    pub async fn scrape_single_activity(
+        &self,
+        activity_id: &str,
+        strava_id: &str,
+        interval: &str,
+        force: bool,
+    ) -> Result<usize, Error> {
+        let activity = self
+            .run_scrapper(strava_id, interval, 0)
+            .await?
+            .into_iter()
+            .find(|a| a.id == activity_id)
+            .ok_or_else(|| {
+                Error::NotFound(
+                    404,
+                    format!("Activity {activity_id} is not in the {interval} feed of athlete {strava_id}"),
+                )
+            })?;
+
+        let parsed = self.parse_activity(&activity, force, 0).await?;
+
+        self.save_activities(vec![parsed], force).await
+    }
 
-                    if let Ok(activities) = Self::run_scrapper(&id, job_id).await {
-                        info!(
-                            "[JOB-{job_id}][{id}] Found {} run activities",
-                            activities.clone().len()
-                        );
+    async fn save_activities(
+        &self,
+        activities: Vec<StravaActivity>,
+        replace: bool,
+    ) -> Result<usize, Error> {
+        if replace {
+            self.store.replace_activities(activities).await
+        } else {
+            self.store.create_activities(activities).await
+        }
+    }
 
-                        let result =
-                            futures::future::join_all(activities.iter().map(async |activity| {
-                                let output = Self::parse_activity(activity, job_id).await;
-                                tokio::time::sleep(Duration::from_secs(2)).await;
-
-                                output
-                            }))
-                            .await
-                            .into_iter()
-                            .filter_map(Result::ok)
-                            .collect::<Vec<StravaActivity>>();
-
-                        match user_repo.create_activities(result).await {
-                            Ok(inserted) => {
-                                info!(
-                                    "[JOB-{job_id}][{id}] Inserted {inserted} activities for athlete {id}"
-                                );
-                                strava_ids.insert(id, inserted);
-                            }
-                            Err(e) => {
-                                error!("[JOB-{job_id}][{id}] Failed to insert activities: {}", e);
-                            }
-                        }
-                    } else {
-                        warn!("[JOB-{job_id}][{id}] No activity data found");
-                    }
+    /// Queue the athletes of the run and process them with `jobs` workers.
+    /// With several shards, only the athletes of this shard are queued, in a
+    /// queue of their own. Fails when a worker could not process the queue,
+    /// or with [`Error::Unauthorized`] when the session expired.
+    pub async fn run(
+        &self,
+        run_id: &str,
+        offset: i64,
+        limit: Option<i64>,
+        jobs: i64,
+    ) -> Result<(), Error> {
+        let run_id = &self.config.scrapper.shard_run_id(run_id);
+        self.enqueue(run_id, offset, limit).await?;
+
+        let workers = (0..jobs).map(|id| {
+            let scrapper = self.clone();
+            let run_id = run_id.to_owned();
+            task::spawn(async move { scrapper.run_worker(&run_id, id).await })
+        });
+
+        let mut result = Ok(());
+        for (id, output) in futures::future::join_all(workers)
+            .await
+            .into_iter()
+            .enumerate()
+        {
+            if let Err(e) = output.map_err(Error::from).and_then(|output| output) {
+                error!("Worker {id} failed: {e}");
+                result = Err(e);
+            }
+        }
 
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-                }
+        if self.is_session_expired() {
+            return Err(Error::Unauthorized(format!(
+                "Strava session expired, run {run_id} stopped"
+            )));
+        }
 
-                strava_ids
-            });
+        result
+    }
 
-            futures.push(task);
+    /// Process jobs of the run until its queue is empty. Fails when the queue
+    /// could not be read, after the other tasks have finished.
+    pub async fn run_worker(&self, run_id: &str, job_id: i64) -> Result<(), Error> {
+        let worker = format!("{:08x}-{job_id}", rand::random::<u32>());
+        let mut futures = FuturesUnordered::new();
+        let mut result = Ok(());
 
-            // Throttle number of concurrent tasks
-            if futures.len() >= MAX_CONCURRENT_TASKS {
-                futures.next().await; // Wait for one to finish
-            }
+        info!("[JOB-{job_id}] Starting scrapper {worker} for run {run_id}");
+
+        for task in 0..self.config.scrapper.max_concurrent_tasks {
+            let scrapper = self.clone();
+            let run_id = run_id.to_owned();
+            // Each task holds its own leases, so a job taken over by another
+            // task is not completed by the one that lost it.
+            let worker = format!("{worker}-{task}");
+
+            futures.push(task::spawn(async move {
+                scrapper.run_task(&run_id, &worker, job_id).await
+            }));
         }
 
+        let (mut athletes, mut activities) = (0, 0);
         while let Some(x) = futures.next().await {
             match x {
-                Ok(ids) => {
+                Ok(Ok(ids)) => {
                     let keys = ids.keys().count();
                     let values = ids.values().sum::<usize>();
+                    athletes += keys;
+                    activities += values;
 
-                    info!(
-                        "[JOB-{job_id}][i] Finished processing athletes {keys} with {values} activities",
+                    debug!(
+                        "[JOB-{job_id}][i] Task finished processing athletes {keys} with {values} activities",
                     );
                 }
+                Ok(Err(e)) => {
+                    error!("[JOB-{job_id}] Failed to lease jobs: {}", e);
+                    result = Err(e);
+                }
                 Err(e) => {
                     error!("[JOB-{job_id}] TaskError: {}", e);
+                    result = Err(e.into());
                 }
             }
         }
 
+        info!(
+            "[JOB-{job_id}][i] Finished processing athletes {athletes} with {activities} activities"
+        );
         info!("[JOB-{job_id}][i] Finished scrapper");
+        self.emit(ScrapeEvent::WorkerFinished { job_id });
 
-        Ok(())
+        result
     }
 
-    async fn run_scrapper(athlete_id: &str, job_id: i64) -> Result<Vec<Activity>, Error> {
-        let now = Utc::now().naive_utc();
-        let year = now.year();
-        let month = now.month();
-        let url = format!(
-            "https://www.strava.com/athletes/{athlete_id}?chart_type=miles&interval_type=month&interval={year}{month:02}&year_offset=0"
-        );
-
-        let mut client: Client;
-        let mut res: reqwest::Response;
-        let mut attempt = 1;
+    /// Lease athletes one by one and scrape them, returning the number of
+    /// inserted activities per athlete.
+    async fn run_task(
+        &self,
+        run_id: &str,
+        worker: &str,
+        job_id: i64,
+    ) -> Result<HashMap<String, usize>, Error> {
+        let lease = Duration::from_secs(self.config.scrapper.lease_secs);
+        let mut strava_ids = HashMap::new();
+
+        while !self.is_shutdown() {
+            let Some(job) = self
+                .store
+                .lease_jobs(run_id, worker, 1, lease)
+                .await?
+                .into_iter()
+                .next()
+            else {
+                break;
+            };
+
+            let id = job.strava_id.clone();
+            let outcome = match self.scrape_athlete(&job, job_id).await {
+                Ok(inserted) => {
+                    self.breaker.record_success();
+                    self.auth_failures().clear();
+                    strava_ids.insert(id.clone(), inserted);
+                    self.store.complete_job(&job).await
+                }
+                Err(Error::Interrupted) => self.store.release_job(&job).await,
+                // The job now belongs to the task which leased it again.
+                Err(Error::Expired(_)) => {
+                    warn!("[JOB-{job_id}][{id}] Lease expired, job taken over by another task");
+                    Ok(())
+                }
+                Err(e) if breaker::is_auth_failure(&e) => {
+                    let failed = self.store.fail_job(&job, &e.to_string()).await;
+                    self.auth_failures().push(job.clone());
 
-        loop {
-            client = utils::build_client(None).await;
-            res = client.get(url.clone()).send().await?;
+                    if self.breaker.record_failure() {
+                        self.trip_breaker(job_id);
+                    }
+                    if self.breaker.is_open() {
+                        self.requeue_auth_failures(job_id).await;
+                    }
 
-            let status = res.headers().get("status").ok_or_else(|| 0).is_ok();
-            if !status {
-                error!("[JOB-{job_id}][{athlete_id}][SKIP] Failed to fetch athlete ID");
-                return Err(error_custom!("Failed to fetch athlete"));
+                    failed
+                }
+                Err(e) => self.store.fail_job(&job, &e.to_string()).await,
+            };
+            if let Err(e) = outcome {
+                error!("[JOB-{job_id}][{id}] Failed to update job: {}", e);
             }
 
-            attempt += 1;
-            if attempt > 5 {
-                error!("[JOB-{job_id}][{athlete_id}][SKIP] Failed to fetch athlete ID");
-                return Err(error_custom!("Failed to fetch athlete"));
-            }
+            tokio::time::sleep(Duration::from_millis(self.config.scrapper.athlete_delay_ms)).await;
+        }
 
-            if res.status().is_success() {
-                break;
+        Ok(strava_ids)
+    }
+
+    fn auth_failures(&self) -> MutexGuard<'_, Vec<ScrapeJob>> {
+        self.auth_failures.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Put the jobs of the athletes which failed as not logged in back in the
+    /// queue, once the breaker says the session expired rather than the
+    /// athletes, so the resumed run scrapes them again.
+    async fn requeue_auth_failures(&self, job_id: i64) {
+        let jobs = std::mem::take(&mut *self.auth_failures());
+
+        for job in jobs {
+            if let Err(e) = self.store.release_job(&job).await {
+                error!(
+                    "[JOB-{job_id}][{}] Failed to requeue job: {}",
+                    job.strava_id, e
+                );
             }
         }
+    }
 
-        let cookie = res.headers().get("cookie").cloned();
-        let status_str = res.status().to_string();
+    fn trip_breaker(&self, job_id: i64) {
+        let failures = self.breaker.failures();
+        error!(
+            "[JOB-{job_id}] {failures} athletes in a row failed as not logged in, the Strava session has expired. Stopping all workers"
+        );
+        self.emit(ScrapeEvent::SessionExpired { failures });
+        self.shutdown();
+    }
 
-        let html = res.text().await?;
-        let document = Document::from_read(html.as_bytes())?;
-        let body = document.find(Name("body")).next().unwrap();
+    /// Scrape the athlete feed of the current month, and of the backfill
+    /// months not scraped yet, returning the number of inserted activities.
+    async fn scrape_athlete(&self, job: &ScrapeJob, job_id: i64) -> Result<usize, Error> {
+        let id = job.strava_id.as_str();
+        let current = month_interval(Utc::now().date_naive());
+        let mut inserted = 0;
 
-        if !body.attr("class").unwrap_or_default().contains("logged-in") {
-            error!("[JOB-{job_id}][SKIP] Not logged in: Session expired! ID: {athlete_id}");
-            error!("[JOB-{job_id}][!!!!!!!!] Cookie: {status_str} {cookie:?}");
-            return Err(error_custom!("Not logged in"));
-        }
+        for interval in self.feed_months(id).await? {
+            if self.is_shutdown() {
+                return Err(Error::Interrupted);
+            }
+            if !self.extend_lease(job, job_id).await {
+                return Err(Error::Expired("Job lease"));
+            }
+
+            let (month_inserted, complete) = self.scrape_month(job, &interval, job_id).await?;
+            inserted += month_inserted;
 
-        let el_activity = body.find(Class("react-feed-component")).next();
-        if el_activity.is_none() {
-            return Err(error_custom!("No activity data found"));
+            // The current month keeps growing, it is fetched on every run. A
+            // month with failed activities is fetched again by the next run.
+            if interval != current
+                && complete
+                && let Err(e) = self.store.save_backfilled_month(id, &interval).await
+            {
+                error!(
+                    "[JOB-{job_id}][{id}] Failed to save backfill of {interval}: {}",
+                    e
+                );
+            }
         }
 
-        if let Some(json_str) = el_activity.unwrap().attr("data-react-props") {
-            let data = serde_json::from_str::<Props>(&json_str);
+        Ok(inserted)
+    }
+
+    /// Extend the lease of the job being scraped, returning `false` when it
+    /// was taken over by another task. Failing to reach the store keeps the
+    /// current lease.
+    async fn extend_lease(&self, job: &ScrapeJob, job_id: i64) -> bool {
+        let lease = Duration::from_secs(self.config.scrapper.lease_secs);
 
-            if data.is_err() {
+        match self.store.extend_lease(job, lease).await {
+            Ok(held) => held,
+            Err(e) => {
                 error!(
-                    "[JOB-{job_id}][{athlete_id}][SKIP] Failed to parse activity data from athlete "
+                    "[JOB-{job_id}][{}] Failed to extend lease: {}",
+                    job.strava_id, e
                 );
-                error!("[JOB-{job_id}] Payload: {json_str}");
-                return Err(error_custom!("Failed to parse activity data"));
+                true
             }
+        }
+    }
 
-            let activities = data
-                .unwrap()
-                .app_context
-                .entries
-                .clone()
-                .into_iter()
-                .filter(|a| {
-                    vec!["Activity", "GroupActivity"].contains(&a.entity.as_str())
-                        && a.activity.clone().is_some_and(|a| a.activity_type == "Run")
-                })
-                .map(|a| a.activity.unwrap())
-                .collect::<Vec<_>>();
-
-            return Ok(activities);
-        } else {
-            error!("[JOB-{job_id}][{athlete_id}] No activity data found from athlete");
+    /// Feed months (`YYYYMM`) to scrape for the athlete, newest first: the
+    /// current month, and the configured backfill range without the months
+    /// already backfilled.
+    async fn feed_months(&self, strava_id: &str) -> Result<Vec<String>, Error> {
+        let today = Utc::now().date_naive();
+        let config = &self.config.scrapper;
+        let Some(since) = config.backfill_since.as_deref().and_then(parse_month) else {
+            return Ok(vec![month_interval(today)]);
+        };
+        let until = config
+            .backfill_until
+            .as_deref()
+            .and_then(parse_month)
+            .unwrap_or(today)
+            .min(today);
+
+        let done = self
+            .store
+            .backfilled_months(strava_id)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let mut months = vec![month_interval(today)];
+        for interval in months_between(since, until).into_iter().map(month_interval) {
+            if !months.contains(&interval) && !done.contains(&interval) {
+                months.push(interval);
+            }
         }
 
-        Err(error_custom!("No activity data found"))
+        Ok(months)
     }
 
-    async fn parse_activity(activity: &Activity, job_id: i64) -> Result<StravaActivity, Error> {
-        let mut repo = REPO.clone();
-        let activity_id = activity.id.clone();
-        if repo.activity_exists(&activity_id).await? {
-            return Err(error_custom!("Activity already exists"));
-        }
+    /// Scrape the athlete feed of one month and save its new activities,
+    /// returning the number of inserted activities and whether every activity
+    /// of the month is now saved. Every outcome is saved in `scrap_logs`.
+    async fn scrape_month(
+        &self,
+        job: &ScrapeJob,
+        interval: &str,
+        job_id: i64,
+    ) -> Result<(usize, bool), Error> {
+        let id = job.strava_id.as_str();
+        let scraped_at = Utc::now().naive_utc();
+        let activities = match self.run_scrapper(id, interval, job_id).await {
+            Ok(activities) => activities,
+            Err(e) => {
+                warn!("[JOB-{job_id}][{id}] No activity data found");
+                self.emit(ScrapeEvent::AthleteSkipped {
+                    job_id,
+                    strava_id: id.to_owned(),
+                    reason: e.to_string(),
+                });
+                self.save_logs(job, job_id, vec![(String::new(), (&e).into())])
+                    .await;
+                return Err(e);
+            }
+        };
 
-        let url = format!("https://www.strava.com/activities/{activity_id}/overview");
-        let mut client: Client;
-        let mut res: reqwest::Response;
-        let mut html: String;
-        let mut attempt = 1;
+        let cursor = AthleteCursor {
+            strava_id: id.to_owned(),
+            user_id: job.user_id,
+            last_scraped_at: scraped_at,
+            last_activity_date: activities
+                .iter()
+                .filter_map(|a| DateTime::parse_from_rfc3339(&a.start_date).ok())
+                .map(|date| date.naive_utc())
+                .max(),
+        };
 
-        loop {
-            client = utils::build_client(None).await;
-            res = client.get(url.clone()).send().await?;
-            let status = res.status();
+        let activities = activities
+            .into_iter()
+            .filter(|a| self.config.scrapper.collects(&a.activity_type))
+            .collect::<Vec<_>>();
 
-            html = res.text().await?;
-            let document = Document::from_read(html.as_bytes())?;
-            let body = document.find(Name("body")).next().unwrap();
-            let inline_stats = body.find(Class("inline-stats")).next();
+        info!(
+            "[JOB-{job_id}][{id}] Found {} activities to scrape in {interval}",
+            activities.len()
+        );
+        self.emit(ScrapeEvent::AthleteFetched {
+            job_id,
+            strava_id: id.to_owned(),
+            activities: activities.len(),
+        });
+
+        let mut outcomes = Vec::with_capacity(activities.len() + 1);
+        let mut complete = true;
+        let lease_lost = AtomicBool::new(false);
+        let result = futures::future::join_all(activities.iter().map(async |activity| {
+            if lease_lost.load(Ordering::SeqCst) {
+                return (activity.id.clone(), Err(Error::Expired("Job lease")));
+            }
 
-            if status.is_success() && inline_stats.is_some() {
-                break;
+            let output = self.parse_activity(activity, false, job_id).await;
+            if !self.extend_lease(job, job_id).await {
+                lease_lost.store(true, Ordering::SeqCst);
+            }
+            self.emit(match &output {
+                Ok(parsed) => ScrapeEvent::ActivityParsed {
+                    job_id,
+                    strava_id: parsed.strava_id.clone(),
+                    activity_id: parsed.activity_id,
+                },
+                Err(e) => ScrapeEvent::ActivitySkipped {
+                    job_id,
+                    activity_id: activity.id.clone(),
+                    reason: e.to_string(),
+                },
+            });
+            tokio::time::sleep(Duration::from_millis(
+                self.config.scrapper.activity_delay_ms,
+            ))
+            .await;
+
+            (activity.id.clone(), output)
+        }))
+        .await
+        .into_iter()
+        .filter_map(|(activity_id, output)| {
+            let status = match &output {
+                Ok(_) => ScrapStatus::Ok,
+                // Left to the task which took the job over.
+                Err(Error::Expired(_)) => return None,
+                Err(Error::AlreadyExists) => ScrapStatus::AlreadyExists,
+                Err(e) => {
+                    complete = false;
+                    e.into()
+                }
+            };
+            outcomes.push((activity_id, status));
+
+            output.ok()
+        })
+        .collect::<Vec<StravaActivity>>();
+
+        let inserted = match self.store.create_activities(result).await {
+            Ok(inserted) => {
+                info!("[JOB-{job_id}][{id}] Inserted {inserted} activities for athlete {id}");
+                self.emit(ScrapeEvent::ActivitiesInserted {
+                    job_id,
+                    strava_id: id.to_owned(),
+                    inserted,
+                });
+                outcomes.push((String::new(), ScrapStatus::Ok));
+
+                Ok((inserted, complete))
             }
+            Err(e) => {
+                error!("[JOB-{job_id}][{id}] Failed to insert activities: {}", e);
+                outcomes.push((String::new(), ScrapStatus::Error));
 
-            attempt += 1;
-            if attempt > 36 {
-                error!("[JOB-{job_id}][{activity_id}][SKIP] Failed to fetch activity ID: {status}");
-                return Err(error_custom!("Failed to fetch activity"));
+                Err(e)
             }
-        }
+        };
+        self.save_logs(job, job_id, outcomes).await;
 
-        let document = Document::from_read(html.as_bytes())?;
-        let body = document.find(Name("body")).next().unwrap();
+        if lease_lost.into_inner() {
+            return Err(Error::Expired("Job lease"));
+        }
 
-        info!(
-            "[JOB-{job_id}][{activity_id}] from {athlete_name} - {activity_name}",
-            activity_id = activity.id,
-            athlete_name = activity.athlete.athlete_name,
-            activity_name = activity.activity_name.trim()
-        );
+        // The athlete only counts as scraped once its activities are saved.
+        if inserted.is_ok()
+            && let Err(e) = self.store.save_cursor(&cursor).await
+        {
+            error!("[JOB-{job_id}][{id}] Failed to save cursor: {}", e);
+        }
 
-        let mut stats: HashMap<String, String> = HashMap::new();
+        inserted
+    }
 
-        let re = Regex::new(r#"(?s)pageView\.activity\(\)\.set\(\{\s*(?P<content>.*?)\s*\}\);"#)
-            .unwrap();
+    /// Save `(activity_id, status)` outcomes of the athlete in `scrap_logs`,
+    /// the athlete feed itself has an empty activity id.
+    async fn save_logs(&self, job: &ScrapeJob, job_id: i64, outcomes: Vec<(String, ScrapStatus)>) {
+        let logs = outcomes
+            .into_iter()
+            .map(|(activity_id, status)| NewScrapLog {
+                run_id: job.run_id.clone(),
+                user_id: job.user_id,
+                strava_id: job.strava_id.clone(),
+                activity_id,
+                status,
+            })
+            .collect();
+
+        if let Err(e) = self.store.create_logs(logs).await {
+            error!(
+                "[JOB-{job_id}][{}] Failed to save scrap logs: {}",
+                job.strava_id, e
+            );
+        }
+    }
 
-        let raw_stats = if let Some(last) = re.captures_iter(&html).last() {
-            let re = Regex::new(r#"(?m)^\s*(\w+)\s*:"#).unwrap();
-            let quoted = re.replace_all(&last["content"], r#""$1":"#);
+    async fn run_scrapper(
+        &self,
+        athlete_id: &str,
+        interval: &str,
+        job_id: i64,
+    ) -> Result<Vec<Activity>, Error> {
+        let policy = RetryPolicy::athlete(&self.config.scrapper);
+        let mut attempt = 0;
 
-            let content = format!("{{{}}}", quoted);
-            serde_json::from_str(&content).expect("Failed to parse raw stats")
-        } else {
-            RawStats::default()
+        let res = loop {
+            attempt += 1;
+            let (retry_after, failure) =
+                match self.fetcher.fetch_athlete(athlete_id, interval).await {
+                    Ok(res) if res.is_success() && res.header("status").is_some() => break res,
+                    Ok(res) if res.is_success() => {
+                        error!("[JOB-{job_id}][{athlete_id}][SKIP] Failed to fetch athlete ID");
+                        return Err(Error::HttpStatus(res.status));
+                    }
+                    // Errors of a proxy or load balancer come without the
+                    // `status` header of Strava, they are retried as well.
+                    Ok(res) if retry::is_retryable_status(res.status) => (
+                        retry::retry_after(&res, self.clock.now()),
+                        Error::HttpStatus(res.status),
+                    ),
+                    Ok(res) => {
+                        error!(
+                            "[JOB-{job_id}][{athlete_id}][SKIP] Failed to fetch athlete ID: {}",
+                            res.status
+                        );
+                        return Err(Error::HttpStatus(res.status));
+                    }
+                    Err(e) if retry::is_retryable_error(&e) => (None, e),
+                    Err(e) => return Err(e),
+                };
+
+            match policy.delay(attempt, retry_after) {
+                Some(delay) => {
+                    // Strava rate limits the session, not only this request.
+                    if retry_after.is_some() {
+                        self.fetcher.pause(delay);
+                    }
+                    warn!(
+                        "[JOB-{job_id}][{athlete_id}] Attempt {attempt} failed: {failure}, retrying in {delay:?}"
+                    );
+                    self.clock.sleep(delay).await;
+                }
+                None => {
+                    error!(
+                        "[JOB-{job_id}][{athlete_id}][SKIP] Failed to fetch athlete ID: {failure}"
+                    );
+                    return Err(failure);
+                }
+            }
         };
 
-        let inline_stats = body.find(Class("inline-stats")).next();
-        if inline_stats.is_none() {
-            error!("[JOB-{job_id}][{activity_id}][SKIP] No inline stats found from activity");
-            return Err(error_custom!("No inline stats found"));
-        }
+        match parser::parse_athlete_feed(&res.body) {
+            Ok(activities) => Ok(activities),
+            Err(ParseError::NotLoggedIn) => {
+                let cookie = res.header("cookie");
+                let status_str = res.status;
 
-        let inline_stats = inline_stats.unwrap();
-        for li in inline_stats.find(Name("li")) {
-            let label = li.find(Class("label")).next().unwrap();
-            let label = label.text().trim().to_case(Case::Snake);
-            let value = li.find(Name("strong")).next().unwrap();
-            let value = value.text().trim().replace(" ", "").to_string();
+                error!("[JOB-{job_id}][SKIP] Not logged in: Session expired! ID: {athlete_id}");
+                error!("[JOB-{job_id}][!!!!!!!!] Cookie: {status_str} {cookie:?}");
+                Err(ParseError::NotLoggedIn.into())
+            }
+            Err(ParseError::InvalidFeed(e)) => {
+                error!(
+                    "[JOB-{job_id}][{athlete_id}][SKIP] Failed to parse activity data from athlete: {e}"
+                );
+                Err(ParseError::InvalidFeed(e).into())
+            }
+            Err(e) => {
+                error!("[JOB-{job_id}][{athlete_id}] No activity data found from athlete");
+                Err(e.into())
+            }
+        }
+    }
 
-            stats.insert(label, value);
+    /// Fetch and parse the overview of a feed activity. Activities already
+    /// saved are skipped unless `force` is set.
+    async fn parse_activity(
+        &self,
+        activity: &Activity,
+        force: bool,
+        job_id: i64,
+    ) -> Result<StravaActivity, Error> {
+        let activity_id = activity.id.clone();
+        if !force && self.store.activity_exists(&activity_id).await? {
+            return Err(Error::AlreadyExists);
         }
 
-        if let Some(more_stats) = body.find(Class("more-stats")).next() {
-            for div in more_stats.find(Class("row")) {
-                let label = div.find(Class("spans5")).next().unwrap();
-                let label = label.text().trim().to_case(Case::Snake);
-                let value = div.find(Class("spans3")).next().unwrap();
-                let value = value.text().trim().replace(" ", "").to_string();
+        let policy = RetryPolicy::activity(&self.config.scrapper);
+        let mut attempt = 0;
 
-                stats.insert(label, value);
+        let html = loop {
+            attempt += 1;
+            let (retry_after, failure) =
+                match self.fetcher.fetch_activity_overview(&activity_id).await {
+                    Ok(res) if res.is_success() && parser::has_inline_stats(&res.body) => {
+                        break res.body;
+                    }
+                    // Strava sometimes serves the overview without its stats.
+                    Ok(res) if res.is_success() => (None, ParseError::NoInlineStats.into()),
+                    Ok(res) if retry::is_retryable_status(res.status) => (
+                        retry::retry_after(&res, self.clock.now()),
+                        Error::HttpStatus(res.status),
+                    ),
+                    Ok(res) => {
+                        error!(
+                            "[JOB-{job_id}][{activity_id}][SKIP] Failed to fetch activity ID: {}",
+                            res.status
+                        );
+                        return Err(Error::HttpStatus(res.status));
+                    }
+                    Err(e) if retry::is_retryable_error(&e) => (None, e),
+                    Err(e) => return Err(e),
+                };
+
+            match policy.delay(attempt, retry_after) {
+                Some(delay) => {
+                    // Strava rate limits the session, not only this request.
+                    if retry_after.is_some() {
+                        self.fetcher.pause(delay);
+                    }
+                    debug!(
+                        "[JOB-{job_id}][{activity_id}] Attempt {attempt} failed: {failure}, retrying in {delay:?}"
+                    );
+                    self.clock.sleep(delay).await;
+                }
+                None => {
+                    error!(
+                        "[JOB-{job_id}][{activity_id}][SKIP] Failed to fetch activity ID: {failure}"
+                    );
+                    return Err(failure);
+                }
             }
-        }
-
-        let result = StravaActivity {
-            activity_id: activity.id.parse().unwrap(),
-            strava_id: activity.athlete.athlete_id.to_owned(),
-            distance_m: raw_stats.distance.map(|f| f.round() as i32),
-            elev_gain_m: raw_stats.elev_gain.map(|f| f.round() as i32),
-            moving_time_s: raw_stats.moving_time.map(|f| f as i32),
-            elapsed_time_s: stats
-                .get("elapsed_time")
-                .map(elapsed_time_to_sec)
-                .unwrap_or_default(),
-            pace_sec_per_km: stats.get("pace").map(pace_to_sec).unwrap_or_default(),
-            pace_text: stats.get("pace").map(|s| s.to_string()),
-            calories: raw_stats.calories.map(|f| f.round() as f32),
-            avg_cadence: raw_stats.avg_cadence.map(|f| f.round() as f32),
-            trainer: raw_stats.trainer,
-            sport_type: Some(activity.activity_type.to_owned().to_lowercase()),
-            athlete_name: Some(activity.athlete.athlete_name.to_owned()),
-            payload: serde_json::to_string(&json!({
-                "activity": activity,
-                "stats": stats,
-                "raw_stats": raw_stats
-            }))
-            .ok(),
-            activity_date: activity.start_date.to_owned(),
-            scraped_at: Utc::now().naive_utc(),
         };
 
-        Ok(result)
+        info!(
+            "[JOB-{job_id}][{activity_id}] from {athlete_name} - {activity_name}",
+            activity_id = activity.id,
+            athlete_name = activity.athlete.athlete_name,
+            activity_name = activity.activity_name.trim()
+        );
+
+        parser::parse_activity_overview(&html, activity).map_err(|e| {
+            error!("[JOB-{job_id}][{activity_id}][SKIP] {e}");
+            e.into()
+        })
     }
 }
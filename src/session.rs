@@ -0,0 +1,82 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{
+    error::{Error, ErrorCode},
+    types::CookieData,
+};
+
+/// Source of Strava login sessions (cookies) used for requests.
+pub trait SessionSource: Send + Sync {
+    /// Pick a session, by index when given or randomly otherwise.
+    fn session(&self, idx: Option<usize>) -> Result<CookieData, Error>;
+}
+
+fn pick(sessions: &[CookieData], idx: Option<usize>) -> Result<CookieData, Error> {
+    if sessions.is_empty() {
+        return Err(Error::Unauthorized(
+            "No Strava session available".to_string(),
+        ));
+    }
+
+    let index = idx.unwrap_or_else(|| rand::random_range(0..sessions.len()));
+    sessions.get(index).cloned().ok_or_else(|| {
+        Error::InvalidParameter(
+            ErrorCode::InvalidParameter as i32,
+            format!("No Strava session at index {index}"),
+        )
+    })
+}
+
+type LoadedSessions = (SystemTime, Vec<CookieData>);
+
+/// Sessions read from a `cookies.json` file. The file is read again only
+/// when it was modified, so it can be updated while the scrapper runs.
+#[derive(Debug, Clone)]
+pub struct CookieFile {
+    path: PathBuf,
+    /// Sessions of the file with its modification time, shared by clones.
+    loaded: Arc<Mutex<Option<LoadedSessions>>>,
+}
+
+impl CookieFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            loaded: Arc::default(),
+        }
+    }
+}
+
+impl SessionSource for CookieFile {
+    fn session(&self, idx: Option<usize>) -> Result<CookieData, Error> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+
+        match &*loaded {
+            Some((loaded_at, sessions)) if *loaded_at == modified => pick(sessions, idx),
+            _ => {
+                let file = fs::read_to_string(&self.path)?;
+                let sessions: Vec<CookieData> = serde_json::from_str(&file)?;
+                let session = pick(&sessions, idx);
+                *loaded = Some((modified, sessions));
+
+                session
+            }
+        }
+    }
+}
+
+/// Fixed list of sessions kept in memory.
+#[derive(Debug, Clone)]
+pub struct StaticSessions(pub Vec<CookieData>);
+
+impl SessionSource for StaticSessions {
+    fn session(&self, idx: Option<usize>) -> Result<CookieData, Error> {
+        pick(&self.0, idx)
+    }
+}
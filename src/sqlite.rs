@@ -0,0 +1,198 @@
+#![allow(dead_code)]
+use std::time::Duration;
+
+use diesel::{
+    connection::SimpleConnection,
+    prelude::*,
+    r2d2::{self, ConnectionManager, CustomizeConnection},
+    sqlite::SqliteConnection,
+};
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+
+use crate::{Error, config::DatabaseConfig, error::ErrorCode};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/sqlite");
+
+pub type SqlitePool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+pub type SqliteConn = r2d2::PooledConnection<ConnectionManager<SqliteConnection>>;
+
+/// SQLite flavour of the tables in [`crate::schema`].
+mod schema {
+    diesel::table! {
+        users (id) {
+            id -> BigInt,
+            name -> Text,
+            email -> Text,
+            strava_id -> Text,
+            created_at -> Timestamp,
+        }
+    }
+
+    diesel::table! {
+        strava_activities (activity_id) {
+            activity_id     -> BigInt,
+            strava_id       -> Text,
+            distance_m      -> Nullable<Integer>,
+            elev_gain_m     -> Nullable<Integer>,
+            moving_time_s   -> Nullable<Integer>,
+            elapsed_time_s  -> Nullable<Integer>,
+            pace_sec_per_km -> Nullable<SmallInt>,
+            pace_text       -> Nullable<Text>,
+            calories        -> Nullable<Float>,
+            avg_cadence     -> Nullable<Float>,
+            trainer         -> Nullable<SmallInt>,
+            sport_type      -> Nullable<Text>,
+            avg_speed_kmh   -> Nullable<Float>,
+            max_speed_kmh   -> Nullable<Float>,
+            avg_power_w     -> Nullable<Integer>,
+            weighted_power_w -> Nullable<Integer>,
+            max_power_w     -> Nullable<Integer>,
+            swim_pace_sec_per_100m -> Nullable<SmallInt>,
+            pool_length_m   -> Nullable<Float>,
+            avg_hr          -> Nullable<Float>,
+            avg_temp        -> Nullable<Float>,
+            use_timer_time  -> Nullable<SmallInt>,
+            workout_type    -> Nullable<SmallInt>,
+            workout_label   -> Nullable<Text>,
+            athlete_name    -> Nullable<Text>,
+            payload         -> Text,
+            scraped_at      -> Timestamp,
+            activity_date   -> Nullable<Text>,
+        }
+    }
+
+    diesel::table! {
+        scrap_logs (id) {
+            id -> BigInt,
+            user_id -> BigInt,
+            strava_id -> Text,
+            activity_id -> Text,
+            status -> Text,
+            created_at -> Timestamp,
+            run_id -> Nullable<Text>,
+        }
+    }
+
+    diesel::table! {
+        scrape_jobs (id) {
+            id -> BigInt,
+            run_id -> Text,
+            user_id -> BigInt,
+            strava_id -> Text,
+            status -> Text,
+            attempts -> Integer,
+            leased_by -> Nullable<Text>,
+            leased_until -> Nullable<Timestamp>,
+            last_error -> Nullable<Text>,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+        }
+    }
+
+    diesel::table! {
+        athlete_cursors (strava_id) {
+            strava_id -> Text,
+            user_id -> BigInt,
+            last_scraped_at -> Timestamp,
+            last_activity_date -> Nullable<Timestamp>,
+        }
+    }
+
+    diesel::table! {
+        athlete_backfills (strava_id, month) {
+            strava_id -> Text,
+            month -> Text,
+            created_at -> Timestamp,
+        }
+    }
+
+    diesel::allow_tables_to_appear_in_same_query!(
+        users,
+        strava_activities,
+        scrap_logs,
+        scrape_jobs,
+        athlete_cursors,
+        athlete_backfills,
+    );
+}
+
+/// Pragmas applied to every pooled connection, so concurrent workers wait for
+/// the write lock instead of failing with `database is locked`.
+#[derive(Debug)]
+struct ConnectionOptions {
+    read_only: bool,
+}
+
+impl CustomizeConnection<SqliteConnection, r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), r2d2::Error> {
+        let pragmas = if self.read_only {
+            "PRAGMA busy_timeout = 10000; PRAGMA query_only = 1;"
+        } else {
+            "PRAGMA busy_timeout = 10000; PRAGMA journal_mode = WAL;"
+        };
+
+        conn.batch_execute(pragmas).map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// [`ActivityStore`] backed by a local SQLite file.
+#[derive(Clone)]
+pub struct SqliteRepository {
+    conn: SqlitePool,
+}
+
+impl SqliteRepository {
+    /// Open (or create) the database file and run pending migrations. The
+    /// pool is sized as configured for MySQL.
+    pub fn open(path: &str, config: &DatabaseConfig) -> Result<Self, Error> {
+        let pool = Self::pool(path, config, false)?;
+
+        pool.get()?
+            .run_pending_migrations(MIGRATIONS)
+            .map_err(|e| {
+                Error::InternalError(
+                    ErrorCode::DatabaseError as i32,
+                    failure::err_msg(e.to_string()),
+                )
+            })?;
+
+        Ok(Self { conn: pool })
+    }
+
+    /// Open an existing database file without writing to it, e.g. for a dry
+    /// run. Pending migrations are not run.
+    pub fn open_read_only(path: &str, config: &DatabaseConfig) -> Result<Self, Error> {
+        let uri = format!(
+            "file:{}?mode=ro",
+            path.replace('%', "%25")
+                .replace('?', "%3f")
+                .replace('#', "%23")
+        );
+
+        Ok(Self {
+            conn: Self::pool(&uri, config, true)?,
+        })
+    }
+
+    fn pool(
+        database_url: &str,
+        config: &DatabaseConfig,
+        read_only: bool,
+    ) -> Result<SqlitePool, Error> {
+        let manager = ConnectionManager::<SqliteConnection>::new(database_url);
+
+        Ok(r2d2::Pool::builder()
+            .max_size(config.pool_max_size)
+            .min_idle(Some(config.pool_min_idle))
+            .connection_timeout(Duration::from_secs(config.conn_timeout_secs))
+            .idle_timeout(Some(Duration::from_secs(config.idle_timeout_secs)))
+            .connection_customizer(Box::new(ConnectionOptions { read_only }))
+            .build(manager)?)
+    }
+
+    pub async fn conn(&self) -> SqliteConn {
+        self.conn.get().expect("Failed to get connection")
+    }
+}
+
+diesel_store!(SqliteRepository, schema = schema, id = i64, flag = i16);
@@ -0,0 +1,173 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    config::DatabaseConfig,
+    error::Error,
+    models::{AthleteCursor, FailureCount, JobCounts, NewScrapLog, ScrapeJob, User, UserCursor},
+    types::StravaActivity,
+};
+
+/// Storage used by the scrapper to read athletes and save their activities.
+pub trait ActivityStore: Send + Sync {
+    /// Get up to `limit` athletes having a Strava ID, newest first, starting
+    /// after `after` or from the newest one.
+    fn get_users<'a>(
+        &'a self,
+        after: Option<&'a UserCursor>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>>;
+
+    /// Position of the `offset`-th athlete returned by
+    /// [`get_users`](Self::get_users), `None` when there are fewer athletes.
+    fn skip_users(&self, offset: i64) -> BoxFuture<'_, Result<Option<UserCursor>, Error>>;
+
+    /// Count the athletes having a Strava ID.
+    fn count_users(&self) -> BoxFuture<'_, Result<i64, Error>>;
+
+    fn activity_exists<'a>(&'a self, activity_id: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Insert activities, ignoring the ones already saved. Returns the number
+    /// of inserted rows.
+    fn create_activities(
+        &self,
+        activities: Vec<StravaActivity>,
+    ) -> BoxFuture<'_, Result<usize, Error>>;
+
+    /// Insert activities, replacing the ones already saved. Returns the number
+    /// of saved activities.
+    fn replace_activities(
+        &self,
+        activities: Vec<StravaActivity>,
+    ) -> BoxFuture<'_, Result<usize, Error>>;
+
+    /// Queue athletes in the `scrape_jobs` table of the run, ignoring the ones
+    /// already queued. Returns the number of new jobs.
+    fn enqueue_jobs<'a>(
+        &'a self,
+        run_id: &'a str,
+        users: Vec<User>,
+    ) -> BoxFuture<'a, Result<usize, Error>>;
+
+    /// Lease up to `count` jobs of the run for `lease`, taking pending jobs
+    /// and jobs whose lease has expired. A job is only given to one worker
+    /// until its lease expires.
+    fn lease_jobs<'a>(
+        &'a self,
+        run_id: &'a str,
+        worker: &'a str,
+        count: i64,
+        lease: Duration,
+    ) -> BoxFuture<'a, Result<Vec<ScrapeJob>, Error>>;
+
+    /// Push the lease of a job back to `lease` from now, while its worker is
+    /// still scraping it. Returns `false` when the lease was taken over by
+    /// another worker.
+    fn extend_lease<'a>(
+        &'a self,
+        job: &'a ScrapeJob,
+        lease: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Mark a leased job as done. Ignored when the lease was taken over by
+    /// another worker.
+    fn complete_job<'a>(&'a self, job: &'a ScrapeJob) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Mark a leased job as failed with the reason.
+    fn fail_job<'a>(
+        &'a self,
+        job: &'a ScrapeJob,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Put a leased job back in the queue, e.g. when the worker stops before
+    /// finishing it.
+    fn release_job<'a>(&'a self, job: &'a ScrapeJob) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Count the jobs of the run per status.
+    fn job_counts<'a>(&'a self, run_id: &'a str) -> BoxFuture<'a, Result<JobCounts, Error>>;
+
+    /// Save outcomes into `scrap_logs`.
+    fn create_logs(&self, logs: Vec<NewScrapLog>) -> BoxFuture<'_, Result<usize, Error>>;
+
+    /// Count failed outcomes per athlete, most failures first, optionally
+    /// for one run only.
+    fn failures_by_athlete<'a>(
+        &'a self,
+        run_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<FailureCount>, Error>>;
+
+    /// Count failed outcomes per run, most failures first.
+    fn failures_by_run(&self) -> BoxFuture<'_, Result<Vec<FailureCount>, Error>>;
+
+    /// Get the cursors of the athletes scraped before.
+    fn get_cursors<'a>(
+        &'a self,
+        strava_ids: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<AthleteCursor>, Error>>;
+
+    /// Save the cursor of an athlete, keeping the newest `last_activity_date`
+    /// of the saved and the given one.
+    fn save_cursor<'a>(&'a self, cursor: &'a AthleteCursor) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Get the feed months (`YYYYMM`) of the athlete already backfilled.
+    fn backfilled_months<'a>(
+        &'a self,
+        strava_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, Error>>;
+
+    /// Remember that the feed month (`YYYYMM`) of the athlete was scraped and
+    /// does not need to be fetched again.
+    fn save_backfilled_month<'a>(
+        &'a self,
+        strava_id: &'a str,
+        month: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Open the store for the configured database URL.
+///
+/// `mysql://...` is served by [`Repository`](crate::Repository), while
+/// `sqlite://<path>` or a plain file path is served by
+/// [`SqliteRepository`](crate::SqliteRepository).
+pub fn connect(config: &DatabaseConfig) -> Result<Arc<dyn ActivityStore>, Error> {
+    open(config, false)
+}
+
+/// Open the store for the configured database URL without writing to it, as
+/// [`connect`] does. A SQLite file is opened read-only and its migrations are
+/// not run.
+pub fn connect_read_only(config: &DatabaseConfig) -> Result<Arc<dyn ActivityStore>, Error> {
+    open(config, true)
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+fn open(config: &DatabaseConfig, read_only: bool) -> Result<Arc<dyn ActivityStore>, Error> {
+    #[cfg(feature = "sqlite")]
+    let sqlite = |path: &str| {
+        use crate::sqlite::SqliteRepository;
+
+        if read_only {
+            SqliteRepository::open_read_only(path, config)
+        } else {
+            SqliteRepository::open(path, config)
+        }
+    };
+    let database_url = config.url.as_str();
+
+    match database_url.split_once("://") {
+        #[cfg(feature = "mysql")]
+        Some(("mysql", _)) => Ok(Arc::new(crate::repository::Repository::new(
+            crate::database::pool(config)?,
+        ))),
+        #[cfg(feature = "sqlite")]
+        Some(("sqlite", path)) => Ok(Arc::new(sqlite(path)?)),
+        #[cfg(feature = "sqlite")]
+        None => Ok(Arc::new(sqlite(database_url)?)),
+        _ => Err(Error::InvalidParameter(
+            crate::error::ErrorCode::InvalidParameter as i32,
+            format!("Unsupported database URL: {database_url}"),
+        )),
+    }
+}
@@ -1,3 +1,5 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,13 +30,205 @@ pub struct Activity {
     pub id: String,
     pub activity_name: String,
     #[serde(rename = "type")]
-    pub activity_type: String,
+    pub activity_type: SportType,
     pub athlete: Athlete,
     pub start_date: String,
     pub start_date_local: Option<String>,
     pub elapsed_time: i64,
 }
 
+macro_rules! sport_types {
+    ($($(#[$doc:meta])* $variant:ident,)+) => {
+        /// Activity type of the Strava feed, e.g. `Run` or `TrailRun`. Types
+        /// not known here are kept as [`SportType::Other`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum SportType {
+            $($(#[$doc])* $variant,)+
+            /// Any other type, as named by Strava.
+            Other(String),
+        }
+
+        impl SportType {
+            /// Names of the types known here.
+            pub const NAMES: &[&str] = &[$(stringify!($variant),)+];
+
+            /// Name used by Strava.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => stringify!($variant),)+
+                    Self::Other(name) => name,
+                }
+            }
+
+            /// Whether the type is one of the types known here.
+            pub fn is_known(&self) -> bool {
+                !matches!(self, Self::Other(_))
+            }
+
+            /// Parse the name of a known type, e.g. from the command line
+            /// where a typo must not select an [`SportType::Other`] type.
+            pub fn parse_known(s: &str) -> Result<Self, String> {
+                let Ok(sport) = s.parse::<Self>();
+                if sport.is_known() {
+                    Ok(sport)
+                } else {
+                    Err(format!(
+                        "unknown activity type `{s}`, expected one of {}",
+                        Self::NAMES.join(", ")
+                    ))
+                }
+            }
+        }
+
+        impl FromStr for SportType {
+            type Err = Infallible;
+
+            /// Parse a Strava name, ignoring case so stored lowercase names
+            /// are read back too.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(if s.eq_ignore_ascii_case(stringify!($variant)) {
+                    return Ok(Self::$variant);
+                })+
+
+                Ok(Self::Other(s.to_string()))
+            }
+        }
+    };
+}
+
+sport_types! {
+    Run,
+    TrailRun,
+    VirtualRun,
+    Walk,
+    Hike,
+    Wheelchair,
+    Ride,
+    MountainBikeRide,
+    GravelRide,
+    EBikeRide,
+    EMountainBikeRide,
+    VirtualRide,
+    Velomobile,
+    Handcycle,
+    Swim,
+    Rowing,
+    VirtualRow,
+    Kayaking,
+    Canoeing,
+    StandUpPaddling,
+    Surfing,
+    AlpineSki,
+    BackcountrySki,
+    NordicSki,
+    Snowboard,
+    Snowshoe,
+    IceSkate,
+    InlineSkate,
+    Workout,
+    WeightTraining,
+    Crossfit,
+    Elliptical,
+    StairStepper,
+    HighIntensityIntervalTraining,
+    Yoga,
+    Pilates,
+    RockClimbing,
+}
+
+impl SportType {
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Run | Self::TrailRun | Self::VirtualRun)
+    }
+
+    pub fn is_walking(&self) -> bool {
+        matches!(self, Self::Walk | Self::Hike)
+    }
+
+    pub fn is_cycling(&self) -> bool {
+        matches!(
+            self,
+            Self::Ride
+                | Self::MountainBikeRide
+                | Self::GravelRide
+                | Self::EBikeRide
+                | Self::EMountainBikeRide
+                | Self::VirtualRide
+                | Self::Velomobile
+                | Self::Handcycle
+        )
+    }
+
+    pub fn is_swimming(&self) -> bool {
+        matches!(self, Self::Swim)
+    }
+
+    /// Lowercase name saved in the `sport_type` column.
+    pub fn column_value(&self) -> String {
+        self.as_str().to_lowercase()
+    }
+}
+
+impl From<String> for SportType {
+    fn from(name: String) -> Self {
+        let Ok(sport) = name.parse();
+        sport
+    }
+}
+
+impl From<SportType> for String {
+    fn from(sport: SportType) -> Self {
+        sport.as_str().to_string()
+    }
+}
+
+impl fmt::Display for SportType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Kind of workout picked by the athlete, given by Strava as a `workout_type`
+/// code: 0 to 3 for runs, 10 to 12 for rides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkoutType {
+    Default,
+    Race,
+    LongRun,
+    Workout,
+}
+
+impl WorkoutType {
+    /// Decode a Strava `workout_type`, `None` for codes not known here.
+    pub fn from_code(code: i64) -> Option<Self> {
+        match code {
+            0 | 10 => Some(Self::Default),
+            1 | 11 => Some(Self::Race),
+            2 => Some(Self::LongRun),
+            3 | 12 => Some(Self::Workout),
+            _ => None,
+        }
+    }
+
+    /// Label saved in the `workout_label` column.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Race => "race",
+            Self::LongRun => "long_run",
+            Self::Workout => "workout",
+        }
+    }
+}
+
+impl fmt::Display for WorkoutType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Athlete {
@@ -58,25 +252,52 @@ pub struct StravaActivity {
     pub calories: Option<f32>,
     pub avg_cadence: Option<f32>,
     pub trainer: Option<bool>,
-    pub sport_type: Option<String>,
+    pub sport_type: Option<SportType>,
+    pub avg_speed_kmh: Option<f32>,
+    pub max_speed_kmh: Option<f32>,
+    pub avg_power_w: Option<i32>,
+    pub weighted_power_w: Option<i32>,
+    pub max_power_w: Option<i32>,
+    pub swim_pace_sec_per_100m: Option<i16>,
+    pub pool_length_m: Option<f32>,
+    pub avg_hr: Option<f32>,
+    pub avg_temp: Option<f32>,
+    pub use_timer_time: Option<bool>,
+    /// Strava `workout_type` code, see [`StravaActivity::workout`].
+    pub workout_type: Option<i16>,
     pub athlete_name: Option<String>,
     pub payload: Option<String>,
     pub scraped_at: chrono::NaiveDateTime,
     pub activity_date: String,
 }
 
+impl StravaActivity {
+    /// Decoded `workout_type`.
+    pub fn workout(&self) -> Option<WorkoutType> {
+        self.workout_type
+            .and_then(|code| WorkoutType::from_code(code.into()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RawStats {
     pub avg_cadence: Option<f64>,
     pub avg_hr: Option<f64>,
+    /// Meters per second.
     pub avg_speed: Option<f64>,
     pub avg_temp: Option<f64>,
+    pub avg_watts: Option<f64>,
     pub calories: Option<f64>,
     pub distance: Option<f64>,
     pub elev_gain: Option<f64>,
+    /// Meters per second.
+    pub max_speed: Option<f64>,
+    pub max_watts: Option<f64>,
     pub moving_time: Option<i64>,
+    pub pool_length: Option<f64>,
     pub trainer: Option<bool>,
     pub use_timer_time: Option<bool>,
+    pub weighted_avg_watts: Option<f64>,
     pub workout_type: Option<i64>,
 }
 
@@ -1,5 +1,6 @@
-use std::{env, fs, sync::Arc};
+use std::{fs, path::Path, sync::Arc, time::Duration};
 
+use chrono::{Datelike, Months, NaiveDate};
 use cookie_store::{Cookie, CookieStore};
 use regex::Regex;
 use reqwest::{
@@ -9,11 +10,14 @@ use reqwest::{
 use reqwest_cookie_store::CookieStoreMutex;
 use select::{document::Document, predicate::Name};
 
-use crate::{CookieData, Error, Proxy};
+use crate::{CookieData, Error, Proxy, config::HttpConfig, fetcher::HttpFetcher};
+
+/// Default base URL of the Strava website.
+pub const STRAVA_URL: &str = "https://www.strava.com";
 
 /// parse HH:MM:SS to seconds or MM:SS to seconds
 /// input: "01:10:10 hours" or "30:00 minutes"
-pub fn elapsed_time_to_sec(elapsed_time: &String) -> Option<i32> {
+pub fn elapsed_time_to_sec(elapsed_time: &str) -> Option<i32> {
     let re = Regex::new(r#"(?m)^\s*(\d+):(\d+):(\d+)\s*"#).unwrap();
     let re2 = Regex::new(r#"(?m)^\s*(\d+):(\d+)\s*"#).unwrap();
 
@@ -21,20 +25,59 @@ pub fn elapsed_time_to_sec(elapsed_time: &String) -> Option<i32> {
         let hours = caps.get(1).unwrap().as_str().parse::<i32>().unwrap();
         let minutes = caps.get(2).unwrap().as_str().parse::<i32>().unwrap();
         let seconds = caps.get(3).unwrap().as_str().parse::<i32>().unwrap();
-        Some((hours * 3600 + minutes * 60 + seconds) as i32)
+        Some(hours * 3600 + minutes * 60 + seconds)
     } else if let Some(caps) = re2.captures(elapsed_time) {
         let minutes = caps.get(1).unwrap().as_str().parse::<i32>().unwrap();
         let seconds = caps.get(2).unwrap().as_str().parse::<i32>().unwrap();
-        Some((minutes * 60 + seconds) as i32)
+        Some(minutes * 60 + seconds)
     } else {
         None
     }
 }
 
-pub fn pace_to_sec(pace: &String) -> Option<i16> {
+/// shard (`0..shard_count`) of the user, from the FNV-1a hash of its id, so
+/// every host assigns users the same way
+pub fn shard_of(user_id: u64, shard_count: u64) -> u64 {
+    let hash = user_id
+        .to_le_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+
+    hash % shard_count.max(1)
+}
+
+/// parse `YYYY-MM` to the first day of the month
+pub fn parse_month(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()
+}
+
+/// format the month as the `YYYYMM` interval of the athlete feed
+pub fn month_interval(month: NaiveDate) -> String {
+    format!("{:04}{:02}", month.year(), month.month())
+}
+
+/// months from `until` back to `since`, both included, newest first
+pub fn months_between(since: NaiveDate, until: NaiveDate) -> Vec<NaiveDate> {
+    let mut months = Vec::new();
+    let mut month = until.with_day(1).unwrap_or(until);
+
+    while month >= since {
+        months.push(month);
+        month = match month.checked_sub_months(Months::new(1)) {
+            Some(prev) => prev,
+            None => break,
+        };
+    }
+
+    months
+}
+
+pub fn pace_to_sec(pace: &str) -> Option<i16> {
     let re = Regex::new(r#"(?m)^\s*(\d+):(\d+)\s*"#).unwrap();
 
-    if let Some(caps) = re.captures(&pace) {
+    if let Some(caps) = re.captures(pace) {
         let minutes = caps.get(1).unwrap().as_str().parse::<i32>().unwrap();
         let seconds = caps.get(2).unwrap().as_str().parse::<i32>().unwrap();
         Some((minutes * 60 + seconds) as i16)
@@ -43,9 +86,41 @@ pub fn pace_to_sec(pace: &String) -> Option<i16> {
     }
 }
 
-pub fn create_cookie_store(idx: Option<usize>) -> Arc<CookieStoreMutex> {
+/// parse the leading number of a stat, e.g. "1,234.5km/h" or "180W"
+pub fn leading_number(value: &str) -> Option<f64> {
+    let re = Regex::new(r#"^\s*(\d[\d,]*(?:\.\d+)?)"#).unwrap();
+
+    re.captures(value)
+        .and_then(|caps| caps[1].replace(',', "").parse().ok())
+}
+
+/// parse a speed stat to km/h, e.g. "30.0km/h" or "18.6mi/h"
+pub fn speed_to_kmh(speed: &str) -> Option<f32> {
+    let value = leading_number(speed)?;
+    let kmh = if speed.contains("mi/h") {
+        value * 1.609344
+    } else {
+        value
+    };
+
+    Some(kmh as f32)
+}
+
+/// parse a swim pace stat to seconds per 100 meters, e.g. "2:00/100m" or
+/// "1:50/100yd"
+pub fn swim_pace_to_sec(pace: &str) -> Option<i16> {
+    let secs = pace_to_sec(pace)?;
+
+    if pace.contains("yd") {
+        Some((secs as f64 / 0.9144).round() as i16)
+    } else {
+        Some(secs)
+    }
+}
+
+pub fn create_cookie_store(cookie_data: &CookieData, base_url: &str) -> Arc<CookieStoreMutex> {
     let mut store = CookieStore::default();
-    let cookie_data = get_cookie(idx).expect("Failed to get cookie");
+    let url = Url::parse(base_url).expect("Invalid base URL");
 
     // debug!("Use cookie from {}", cookie_data.email);
 
@@ -53,15 +128,8 @@ pub fn create_cookie_store(idx: Option<usize>) -> Arc<CookieStoreMutex> {
         let trimmed = pair.trim();
         if let Some((name, value)) = trimmed.split_once('=') {
             let cookie_str = format!("{}={}", name.trim(), value.trim());
-            if let Ok(cookie) =
-                Cookie::parse(&cookie_str, &Url::parse("https://www.strava.com").unwrap())
-            {
-                store
-                    .insert(
-                        cookie.into_owned(),
-                        &Url::parse("https://www.strava.com").unwrap(),
-                    )
-                    .unwrap();
+            if let Ok(cookie) = Cookie::parse(&cookie_str, &url) {
+                store.insert(cookie.into_owned(), &url).unwrap();
             }
         }
     }
@@ -69,8 +137,9 @@ pub fn create_cookie_store(idx: Option<usize>) -> Arc<CookieStoreMutex> {
     Arc::new(CookieStoreMutex::new(store))
 }
 
-pub async fn build_client(idx: Option<usize>) -> Client {
-    let cookie_store = create_cookie_store(idx);
+/// Build a client sending the session cookies, through `proxy` if any.
+pub fn build_client(config: &HttpConfig, session: &CookieData, proxy: Option<&str>) -> Client {
+    let cookie_store = create_cookie_store(session, &config.base_url);
     let mut client = ClientBuilder::new()
         .default_headers({
             let mut headers = HeaderMap::new();
@@ -82,70 +151,62 @@ pub async fn build_client(idx: Option<usize>) -> Client {
             headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
             headers
         })
-        .connect_timeout(std::time::Duration::from_secs(15))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
         .cookie_provider(Arc::clone(&cookie_store));
 
-    let use_proxy: bool = env::var("USE_PROXY")
-        .unwrap_or("false".to_string())
-        .parse()
-        .unwrap();
-    if use_proxy {
-        if let Some(mut proxy) = get_proxy() {
-            loop {
-                let check = reqwest::Client::builder()
-                    .proxy(reqwest::Proxy::http(&proxy).unwrap())
-                    .connect_timeout(std::time::Duration::from_secs(3))
-                    .build()
-                    .unwrap()
-                    .get("https://www.google.com")
-                    .send()
-                    .await
-                    .ok();
-
-                if check.is_some_and(|res| res.status().is_success()) {
-                    break;
-                } else {
-                    proxy = get_proxy().unwrap();
-                }
-            }
-            client = client.proxy(reqwest::Proxy::http(&proxy).unwrap());
-        }
+    if let Some(proxy) = proxy {
+        client = client.proxy(reqwest::Proxy::http(proxy).unwrap());
     }
 
     client.build().expect("Failed to build client")
 }
 
-pub async fn check_cookies(athlete_id: &str) -> Result<(), Error> {
-    let client = build_client(None).await;
-    let res = client
-        .get(format!("https://www.strava.com/athletes/{athlete_id}"))
+/// Whether a request through `proxy` gets an answer.
+pub async fn check_proxy(proxy: &str) -> bool {
+    let Ok(proxy) = reqwest::Proxy::http(proxy) else {
+        return false;
+    };
+    let check = reqwest::Client::builder()
+        .proxy(proxy)
+        .connect_timeout(Duration::from_secs(3))
+        .build()
+        .unwrap()
+        .get("https://www.google.com")
         .send()
-        .await?;
+        .await
+        .ok();
 
-    let status = res.status();
-    let html = res.text().await?;
-    let document = Document::from_read(html.as_bytes())?;
+    check.is_some_and(|res| res.status().is_success())
+}
+
+pub async fn check_cookies(fetcher: &HttpFetcher, athlete_id: &str) -> Result<(), Error> {
+    let res = fetcher.fetch_athlete_page(athlete_id).await?;
+    let document = Document::from_read(res.body.as_bytes())?;
     let body = document.find(Name("body")).next().unwrap();
 
     if !body.attr("class").unwrap_or_default().contains("logged-in") {
         error!("Cookies expired. Please login again.");
 
-        error!("Error: {status}");
+        error!("Error: {}", res.status);
         return Err(error_custom!("Not logged in. Session expired!"));
     }
 
     Ok(())
 }
 
-pub fn get_proxy() -> Option<String> {
-    let file = fs::read_to_string("./proxies.json").expect("Unable to read proxy file");
-    let proxies: Vec<Proxy> = serde_json::from_str(&file).expect("Unable to parse proxy file");
-    let proxies = proxies
-        .iter()
+/// Read the alive proxies of the proxy list file.
+pub fn load_proxies(path: &Path) -> Result<Vec<String>, Error> {
+    let file = fs::read_to_string(path)?;
+    let proxies: Vec<Proxy> = serde_json::from_str(&file)?;
+
+    Ok(proxies
+        .into_iter()
         .filter(|p| p.alive)
-        .map(|addr| addr.proxy.clone())
-        .collect::<Vec<String>>();
+        .map(|p| p.proxy)
+        .collect())
+}
 
+pub fn pick_proxy(proxies: &[String]) -> Option<String> {
     if proxies.is_empty() {
         None
     } else {
@@ -153,12 +214,3 @@ pub fn get_proxy() -> Option<String> {
         Some(proxies[index].clone())
     }
 }
-
-pub fn get_cookie(idx: Option<usize>) -> Option<CookieData> {
-    let file = fs::read_to_string("./cookies.json").expect("Unable to read cookie file");
-    let cookies: Vec<CookieData> =
-        serde_json::from_str(&file).expect("Unable to parse cookie file");
-    let index = rand::random_range(0..cookies.len());
-
-    cookies.get(idx.unwrap_or(index)).cloned()
-}
@@ -0,0 +1,112 @@
+//! Responses recorded by `RecordingFetcher` and served by `ReplayFetcher`.
+
+use std::{
+    collections::VecDeque,
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use asnrun_scrapper::{Error, FetchResponse, Fetcher, RecordingFetcher, ReplayFetcher};
+use futures::future::BoxFuture;
+use tempfile::TempDir;
+
+/// Fetcher answering with the given statuses in turn.
+struct Statuses(Mutex<VecDeque<u16>>);
+
+impl Statuses {
+    fn new(statuses: &[u16]) -> Arc<Self> {
+        Arc::new(Self(Mutex::new(statuses.iter().copied().collect())))
+    }
+
+    fn next(&self, url: String) -> BoxFuture<'_, Result<FetchResponse, Error>> {
+        let status = self.0.lock().unwrap().pop_front().unwrap_or(200);
+
+        Box::pin(async move {
+            Ok(FetchResponse {
+                url,
+                status,
+                body: format!("attempt answered with {status}"),
+                ..Default::default()
+            })
+        })
+    }
+}
+
+impl Fetcher for Statuses {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        self.next(format!("/athletes/{athlete_id}?interval={interval}"))
+    }
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        self.next(format!("/activities/{activity_id}/overview"))
+    }
+}
+
+async fn statuses(fetcher: &dyn Fetcher, fetches: usize) -> Vec<u16> {
+    let mut statuses = Vec::new();
+    for _ in 0..fetches {
+        statuses.push(fetcher.fetch_activity_overview("111").await.unwrap().status);
+    }
+
+    statuses
+}
+
+#[tokio::test]
+async fn replays_every_recorded_attempt_in_order() {
+    let dir = TempDir::new().unwrap();
+    let recorder = RecordingFetcher::new(Statuses::new(&[429, 500, 200]), dir.path());
+    assert_eq!(statuses(&recorder, 3).await, [429, 500, 200]);
+
+    let replay = ReplayFetcher::new(dir.path());
+    // The last attempt answers any further fetch.
+    assert_eq!(statuses(&replay, 4).await, [429, 500, 200, 200]);
+}
+
+#[tokio::test]
+async fn starts_a_new_recording_for_each_fetcher() {
+    let dir = TempDir::new().unwrap();
+    let first = RecordingFetcher::new(Statuses::new(&[500, 200]), dir.path());
+    statuses(&first, 2).await;
+
+    let second = RecordingFetcher::new(Statuses::new(&[404]), dir.path());
+    statuses(&second, 1).await;
+
+    let replay = ReplayFetcher::new(dir.path());
+    assert_eq!(statuses(&replay, 2).await, [404, 404]);
+}
+
+#[tokio::test]
+async fn replays_single_response_cassettes() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("activities")).unwrap();
+    let res = FetchResponse {
+        status: 200,
+        ..Default::default()
+    };
+    fs::write(
+        dir.path().join("activities/111.json"),
+        serde_json::to_vec(&res).unwrap(),
+    )
+    .unwrap();
+
+    let replay = ReplayFetcher::new(dir.path());
+    assert_eq!(statuses(&replay, 2).await, [200, 200]);
+}
+
+#[tokio::test]
+async fn fetches_when_the_cassette_cannot_be_written() {
+    let dir = TempDir::new().unwrap();
+    // A file where the cassette directory should be.
+    let root = dir.path().join("cassette");
+    fs::write(&root, "").unwrap();
+
+    let recorder = RecordingFetcher::new(Statuses::new(&[200]), &root);
+    assert_eq!(statuses(&recorder, 1).await, [200]);
+}
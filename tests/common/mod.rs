@@ -0,0 +1,121 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use asnrun_scrapper::{
+    ActivityStore, Clock, Config, DatabaseConfig, ScrapeEvent, SportType, SqliteRepository, User,
+};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use tempfile::TempDir;
+
+pub const RUN_ID: &str = "test-run";
+
+/// Pages of strava.com saved under `tests/fixtures`.
+pub fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Clock whose pauses return at once, recording how long they were.
+#[derive(Default)]
+pub struct FakeClock {
+    slept: Mutex<Vec<Duration>>,
+}
+
+impl FakeClock {
+    pub fn slept(&self) -> Vec<Duration> {
+        self.slept.lock().unwrap().clone()
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        self.slept.lock().unwrap().push(duration);
+        Box::pin(async {})
+    }
+}
+
+/// Config scraping every sport of the fixtures without rate limit.
+pub fn config() -> Config {
+    let mut config = Config::default();
+    config.http.requests_per_sec = 1000.0;
+    config.http.burst = 100;
+    config.scrapper.max_concurrent_tasks = 2;
+    config.scrapper.sports = vec![
+        SportType::Run,
+        SportType::TrailRun,
+        SportType::Ride,
+        SportType::Swim,
+    ];
+
+    config
+}
+
+/// SQLite store in a temporary directory, removed with the returned guard.
+pub fn store() -> (Arc<SqliteRepository>, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("asnrun.db");
+    let store = SqliteRepository::open(path.to_str().unwrap(), &DatabaseConfig::default()).unwrap();
+
+    (Arc::new(store), dir)
+}
+
+/// Queue the athletes for [`RUN_ID`].
+pub async fn enqueue(store: &SqliteRepository, strava_ids: &[&str]) {
+    let users = strava_ids
+        .iter()
+        .enumerate()
+        .map(|(i, strava_id)| User {
+            id: i as u64 + 1,
+            name: format!("Athlete {strava_id}"),
+            email: format!("{strava_id}@example.com"),
+            strava_id: strava_id.to_string(),
+            created_at: Utc::now().naive_utc(),
+        })
+        .collect();
+
+    store.enqueue_jobs(RUN_ID, users).await.unwrap();
+}
+
+/// Events of a scrapper, collected for assertions.
+#[derive(Clone, Default)]
+pub struct Events(Arc<Mutex<Vec<ScrapeEvent>>>);
+
+impl Events {
+    pub fn push(&self, event: &ScrapeEvent) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+
+    pub fn inserted(&self) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| match event {
+                ScrapeEvent::ActivitiesInserted { inserted, .. } => *inserted,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn skipped_activities(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                ScrapeEvent::ActivitySkipped { activity_id, .. } => Some(activity_id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
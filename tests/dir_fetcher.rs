@@ -0,0 +1,127 @@
+//! Parsers and store run over the saved pages of `tests/fixtures`.
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use std::{fs, sync::Arc};
+
+use asnrun_scrapper::{
+    ActivityStore, DirFetcher, Error, FetchResponse, Fetcher, Scrapper, SportType, StaticSessions,
+    parse_activity_overview, parse_athlete_feed,
+};
+use common::{Events, FakeClock, RUN_ID};
+use futures::future::BoxFuture;
+
+fn fixture(path: &str) -> String {
+    fs::read_to_string(common::fixtures().join(path)).unwrap()
+}
+
+/// [`DirFetcher`] answering without the `status` header of strava.com.
+struct NoStatusHeader(DirFetcher);
+
+impl Fetcher for NoStatusHeader {
+    fn fetch_athlete<'a>(
+        &'a self,
+        athlete_id: &'a str,
+        interval: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        Box::pin(async move {
+            let mut res = self.0.fetch_athlete(athlete_id, interval).await?;
+            res.headers.clear();
+            Ok(res)
+        })
+    }
+
+    fn fetch_activity_overview<'a>(
+        &'a self,
+        activity_id: &'a str,
+    ) -> BoxFuture<'a, Result<FetchResponse, Error>> {
+        self.0.fetch_activity_overview(activity_id)
+    }
+}
+
+fn scrapper(fetcher: Arc<dyn Fetcher>, store: Arc<dyn ActivityStore>, events: &Events) -> Scrapper {
+    let events = events.clone();
+
+    Scrapper::builder()
+        .config(common::config())
+        .store(store)
+        .fetcher(fetcher)
+        .session_source(Arc::new(StaticSessions(Vec::new())))
+        .clock(Arc::new(FakeClock::default()))
+        .on_event(move |event| events.push(event))
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn parses_the_athlete_feed() {
+    let activities = parse_athlete_feed(&fixture("athletes/1.html")).unwrap();
+
+    let ids: Vec<_> = activities.iter().map(|a| a.id.as_str()).collect();
+    assert_eq!(ids, ["111", "112", "113", "114"]);
+    assert_eq!(activities[0].activity_type, SportType::Run);
+    assert_eq!(activities[2].activity_type, SportType::TrailRun);
+    assert_eq!(activities[0].athlete.athlete_id, "1");
+}
+
+#[test]
+fn parses_the_activity_overviews() {
+    let feed = parse_athlete_feed(&fixture("athletes/1.html")).unwrap();
+
+    let run = parse_activity_overview(&fixture("activities/111.html"), &feed[0]).unwrap();
+    assert_eq!(run.activity_id, 111);
+    assert_eq!(run.strava_id, "1");
+    assert_eq!(run.distance_m, Some(5000));
+    assert_eq!(run.moving_time_s, Some(1650));
+    assert_eq!(run.elapsed_time_s, Some(1800));
+    assert_eq!(run.pace_sec_per_km, Some(330));
+    assert_eq!(run.workout_type, Some(1));
+
+    let ride = parse_activity_overview(&fixture("activities/112.html"), &feed[1]).unwrap();
+    assert_eq!(ride.sport_type, Some(SportType::Ride));
+    assert_eq!(ride.distance_m, Some(30000));
+    assert_eq!(ride.max_speed_kmh, Some(45.2));
+    assert_eq!(ride.avg_power_w, Some(160));
+    assert_eq!(ride.max_power_w, Some(540));
+    assert_eq!(ride.pace_sec_per_km, None);
+}
+
+#[tokio::test]
+async fn stores_the_activities_of_the_fixtures() {
+    let (store, _dir) = common::store();
+    common::enqueue(&store, &["1", "2", "3"]).await;
+    let events = Events::default();
+    let fetcher = Arc::new(DirFetcher::new(common::fixtures()));
+
+    scrapper(fetcher, store.clone(), &events)
+        .run_worker(RUN_ID, 0)
+        .await
+        .unwrap();
+
+    for activity_id in ["111", "112", "113", "114", "211", "311"] {
+        assert!(
+            store.activity_exists(activity_id).await.unwrap(),
+            "{activity_id} not saved"
+        );
+    }
+    assert_eq!(events.inserted(), 6);
+    let jobs = store.job_counts(RUN_ID).await.unwrap();
+    assert_eq!((jobs.done, jobs.failed), (3, 0));
+}
+
+#[tokio::test]
+async fn rejects_athlete_pages_without_the_status_header() {
+    let (store, _dir) = common::store();
+    common::enqueue(&store, &["1"]).await;
+    let events = Events::default();
+    let fetcher = Arc::new(NoStatusHeader(DirFetcher::new(common::fixtures())));
+
+    scrapper(fetcher, store.clone(), &events)
+        .run_worker(RUN_ID, 0)
+        .await
+        .unwrap();
+
+    assert!(!store.activity_exists("111").await.unwrap());
+    assert_eq!(store.job_counts(RUN_ID).await.unwrap().failed, 1);
+}
@@ -0,0 +1,178 @@
+//! Workers scraping the mock Strava server into a SQLite store.
+#![cfg(all(feature = "sqlite", feature = "mock"))]
+
+mod common;
+
+use std::sync::Arc;
+
+use asnrun_scrapper::{
+    ActivityStore, Config, CookieData, MockResponse, MockStrava, Scrapper, SqliteRepository,
+    StaticSessions,
+};
+use common::{Events, FakeClock, RUN_ID};
+use tempfile::TempDir;
+
+struct Harness {
+    mock: Arc<MockStrava>,
+    store: Arc<SqliteRepository>,
+    clock: Arc<FakeClock>,
+    events: Events,
+    _dir: TempDir,
+}
+
+impl Harness {
+    /// Mock server of the fixtures, scripted by `setup` before it starts.
+    async fn start(setup: impl FnOnce(&MockStrava)) -> Self {
+        let mock = MockStrava::new(common::fixtures());
+        setup(&mock);
+        let (store, dir) = common::store();
+
+        Self {
+            mock,
+            store,
+            clock: Arc::new(FakeClock::default()),
+            events: Events::default(),
+            _dir: dir,
+        }
+    }
+
+    async fn scrapper(&self, mut config: Config) -> Scrapper {
+        let addr = self
+            .mock
+            .start("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        config.http.base_url = format!("http://{addr}");
+        let sessions = StaticSessions(vec![CookieData {
+            email: "test@example.com".to_string(),
+            cookie: "_strava4_session=test".to_string(),
+        }]);
+        let events = self.events.clone();
+
+        Scrapper::builder()
+            .config(config)
+            .store(self.store.clone())
+            .session_source(Arc::new(sessions))
+            .clock(self.clock.clone())
+            .on_event(move |event| events.push(event))
+            .build()
+            .unwrap()
+    }
+
+    async fn exists(&self, activity_id: &str) -> bool {
+        self.store.activity_exists(activity_id).await.unwrap()
+    }
+
+    /// Athletes of `strava_ids` with a saved cursor, sorted.
+    async fn cursors(&self, strava_ids: &[&str]) -> Vec<String> {
+        let strava_ids = strava_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        let mut scraped = self
+            .store
+            .get_cursors(&strava_ids)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|cursor| cursor.strava_id)
+            .collect::<Vec<_>>();
+        scraped.sort();
+
+        scraped
+    }
+}
+
+#[tokio::test]
+async fn scrapes_every_activity_of_the_athletes() {
+    let harness = Harness::start(|_| {}).await;
+    common::enqueue(&harness.store, &["1", "2"]).await;
+    let scrapper = harness.scrapper(common::config()).await;
+
+    scrapper.run_worker(RUN_ID, 0).await.unwrap();
+
+    for activity_id in ["111", "112", "113", "114", "211"] {
+        assert!(harness.exists(activity_id).await, "{activity_id} not saved");
+    }
+    assert_eq!(harness.events.inserted(), 5);
+    assert_eq!(harness.mock.hits("/athletes/1"), 1);
+    assert_eq!(harness.mock.hits("/activities/111/overview"), 1);
+
+    let jobs = harness.store.job_counts(RUN_ID).await.unwrap();
+    assert_eq!((jobs.done, jobs.pending, jobs.failed), (2, 0, 0));
+    assert_eq!(harness.cursors(&["1", "2"]).await, ["1", "2"]);
+}
+
+#[tokio::test]
+async fn retries_rate_limited_and_failing_requests() {
+    let harness = Harness::start(|mock| {
+        mock.script("/athletes/1", [MockResponse::Status(500)]);
+        mock.script(
+            "/activities/111/overview",
+            [MockResponse::Status(429), MockResponse::Status(500)],
+        );
+    })
+    .await;
+    common::enqueue(&harness.store, &["1"]).await;
+    let scrapper = harness.scrapper(common::config()).await;
+
+    scrapper.run_worker(RUN_ID, 0).await.unwrap();
+
+    assert_eq!(harness.mock.hits("/athletes/1"), 2);
+    assert_eq!(harness.mock.hits("/activities/111/overview"), 3);
+    assert!(harness.exists("111").await);
+    // The mock asks to retry a 429 after 1 second.
+    assert!(
+        harness
+            .clock
+            .slept()
+            .contains(&std::time::Duration::from_secs(1))
+    );
+    assert_eq!(harness.store.job_counts(RUN_ID).await.unwrap().done, 1);
+}
+
+#[tokio::test]
+async fn skips_activities_without_inline_stats() {
+    let harness = Harness::start(|mock| {
+        mock.respond("/activities/112/overview", MockResponse::MissingInlineStats);
+    })
+    .await;
+    common::enqueue(&harness.store, &["1"]).await;
+    let config = common::config();
+    let retries = config.scrapper.activity_retries as usize;
+    let scrapper = harness.scrapper(config).await;
+
+    scrapper.run_worker(RUN_ID, 0).await.unwrap();
+
+    assert_eq!(harness.mock.hits("/activities/112/overview"), retries);
+    assert_eq!(harness.events.skipped_activities(), ["112"]);
+    assert!(!harness.exists("112").await);
+    assert!(harness.exists("111").await);
+    assert_eq!(harness.store.job_counts(RUN_ID).await.unwrap().done, 1);
+}
+
+#[tokio::test]
+async fn logged_out_athletes_trip_the_breaker() {
+    let harness = Harness::start(|mock| {
+        for athlete in ["1", "2", "3"] {
+            mock.respond(&format!("/athletes/{athlete}"), MockResponse::LoggedOut);
+        }
+    })
+    .await;
+    common::enqueue(&harness.store, &["1", "2", "3"]).await;
+    let mut config = common::config();
+    config.scrapper.max_concurrent_tasks = 1;
+    config.scrapper.max_auth_failures = 2;
+    let scrapper = harness.scrapper(config).await;
+
+    scrapper.run_worker(RUN_ID, 0).await.unwrap();
+
+    assert!(scrapper.is_session_expired());
+    assert!(scrapper.is_shutdown());
+    assert_eq!(harness.mock.hits("/athletes/3"), 0);
+    // Athletes which failed before the breaker opened are scraped again by
+    // the resumed run.
+    let jobs = harness.store.job_counts(RUN_ID).await.unwrap();
+    assert_eq!((jobs.pending, jobs.failed), (3, 0));
+    assert!(harness.cursors(&["1", "2", "3"]).await.is_empty());
+}